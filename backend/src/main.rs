@@ -1,19 +1,27 @@
 use axum::{
     routing::{get, post},
     Router, Json,
-    http::Method,
-    extract::{Query, Json as JsonExtractor, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    extract::{Path, Query, Json as JsonExtractor},
+    response::{IntoResponse, sse::{Event, KeepAlive, Sse}},
 };
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Serialize};
 use rand::seq::SliceRandom;
 use tower_http::cors::{CorsLayer, Any, AllowHeaders};
-use std::sync::Mutex;
+use tower_http::{limit::RequestBodyLimitLayer, timeout::TimeoutLayer};
 use once_cell::sync::Lazy;
-use std::cmp::Ordering;
-use chrono;
-use rand::Rng;
-use std::sync::Arc;
-use tokio::sync::Mutex as TokioMutex;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::sync::{broadcast, Mutex as TokioMutex};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt;
+use std::fmt::Write as _;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Suit {
@@ -57,13 +65,13 @@ pub enum Rank {
     Ace,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Card {
     suit: Suit,
     rank: Rank,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Player {
     cards: Vec<Card>,
     win_probability: f64,
@@ -71,10 +79,27 @@ pub struct Player {
     is_robot: bool,
     name: String,
     current_bet: u32,  // Track current bet for this round
+    total_bet: u32,  // Chips committed to the pot this hand
     personality: Option<RobotPersonality>,  // Only for robots
+    #[serde(default)]
+    equity_history: Vec<(GamePhase, f64)>,  // Win probability on each street of this hand
+    #[serde(default)]
+    time_bank_secs: u32,  // Extra thinking time left once the action timeout runs out
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Player {
+    /// Still in the hand but with every chip already committed.
+    fn is_all_in(&self) -> bool {
+        !self.cards.is_empty() && self.chips == 0
+    }
+
+    /// Whether the player has any decision left to make this hand.
+    fn can_act(&self) -> bool {
+        !self.cards.is_empty() && !self.is_all_in()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RobotPersonality {
     name: String,
     emoji: String,
@@ -94,6 +119,8 @@ pub struct PlayerStats {
     biggest_pot: u32,
     best_hand: String,
     favorite_action: String,
+    #[serde(default)]
+    rebuys: u32,  // Times the player bought back in after busting
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,22 +129,117 @@ pub struct GameStats {
     end_time: Option<chrono::DateTime<chrono::Utc>>,
     players: Vec<PlayerStats>,
     total_hands: u32,
+    total_pot: u64,  // Sum of all completed-hand pots, for the average
     average_pot: u32,
     biggest_pot: u32,
+    #[serde(default)]
+    total_rake: u64,  // Chips the house has taken out of showdown pots
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
-    deck: Vec<Card>,
+    #[serde(skip_serializing, default)]
+    deck: Vec<Card>,  // Never sent to clients, who could otherwise read the cards still to come
     players: Vec<Player>,
     community_cards: Vec<Card>,
     pot: u32,
     current_bet: u32,
+    #[serde(default)]
+    last_raise: u32,  // Size of the largest bet or raise increment this betting round
     game_mode: GameMode,
     current_player: usize,
+    variant: Variant,
+    dealer: usize,
+    small_blind: u32,
+    big_blind: u32,
+    #[serde(default)]
+    ante: u32,
+    #[serde(default)]
+    straddle: bool,  // The player after the big blind posts a live double big blind
+    #[serde(default)]
+    straddles: u32,  // How many consecutive straddles are posted, each doubling the last
+    #[serde(default)]
+    last_straddler: Option<usize>,  // The seat that posted the final straddle this hand, last to act preflop
     last_action: Option<Action>,
+    equity_mode: EquityMode,
     stats: GameStats,
     hand_history: Vec<HandHistory>,
+    winner: Option<usize>,  // Set once a single player holds all the chips
+    action_timeout_secs: Option<u64>,
+    action_deadline: Option<chrono::DateTime<chrono::Utc>>,  // When the waiting human gets auto-acted
+    #[serde(default = "default_difficulty")]
+    difficulty: f64,  // 0.0 (easy) to 1.0 (hard): how accurately robots judge their equity
+    #[serde(default = "default_show_probabilities")]
+    show_probabilities: bool,  // Whether the human is shown win probabilities
+    #[serde(default)]
+    betting_structure: BettingStructure,
+    #[serde(default)]
+    robot_delay_ms: Option<u64>,  // How long each robot thinks before acting in RobotPlay
+    #[serde(default)]
+    allow_rebuy: bool,  // Whether busted humans may buy back in
+    #[serde(default)]
+    rebuy_amount: u32,  // The stack a rebuy restores
+    #[serde(default)]
+    time_bank_secs: u32,  // Each player's full time bank; none when zero
+    #[serde(default)]
+    blind_seats: Option<(usize, usize)>,  // Small and big blind seats of the hand in play
+    #[serde(default)]
+    rake_percent: f64,  // Fraction of each showdown pot the house takes
+    #[serde(default)]
+    rake_cap: u32,  // Most rake taken from one pot; no cap when zero
+    #[serde(default)]
+    drawn: Vec<usize>,  // Seats that have taken their draw this hand in five-card draw
+    #[serde(default)]
+    discards: Vec<Card>,  // Folded hands and spent second-run cards, out of play until the next hand
+    #[serde(default)]
+    seed: Option<u64>,  // When set, every shuffle and robot decision follows from it
+    #[serde(default)]
+    version: u64,  // Bumped each time a change is published
+    #[serde(skip)]
+    snapshots: VecDeque<StateSnapshot>,  // What clients saw at recent versions, oldest first
+}
+
+/// A game together with its deck, for saving and for the debug `/state` endpoint.
+#[derive(Debug, Serialize)]
+pub struct FullGameState {
+    #[serde(flatten)]
+    game: GameState,
+    deck: Vec<Card>,
+}
+
+/// How many recent versions are kept to compute `/diff` responses against.
+const MAX_SNAPSHOTS: usize = 32;
+
+/// The parts of the state clients redraw after each change, as published at `version`.
+#[derive(Debug, Clone, PartialEq)]
+struct StateSnapshot {
+    version: u64,
+    players: Vec<Player>,
+    pot: u32,
+    community_cards: Vec<Card>,
+    phase: GamePhase,
+    current_player: usize,
+    current_bet: u32,
+}
+
+/// What changed since the version a client last saw. Unchanged fields are left out; when that
+/// version is too old to diff against, `full` is set and every field is included.
+#[derive(Debug, Serialize)]
+pub struct StateDiff {
+    version: u64,
+    full: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    players: BTreeMap<usize, Player>,  // Changed players by seat
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pot: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    community_cards: Option<Vec<Card>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase: Option<GamePhase>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_player: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_bet: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -128,6 +250,119 @@ pub enum GameMode {
     RobotPlay,
 }
 
+/// The poker variant being played, which decides how many hole cards are dealt and how
+/// they combine with the board.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum Variant {
+    /// Two hole cards; the best five of the seven available cards plays.
+    #[default]
+    #[serde(rename = "Holdem")]
+    Holdem,
+    /// Four hole cards; a hand must use exactly two of them with exactly three board cards.
+    #[serde(rename = "Omaha")]
+    Omaha,
+    /// Hold'em with the twos through fives removed: A-6-7-8-9 is the lowest straight and a flush
+    /// beats a full house.
+    #[serde(rename = "ShortDeck")]
+    ShortDeck,
+    /// Five private cards and no board; once the betting is done each player may discard and
+    /// draw replacements once before the showdown.
+    #[serde(rename = "FiveCardDraw")]
+    FiveCardDraw,
+}
+
+/// How large a bet or raise may be.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum BettingStructure {
+    /// Anything up to the player's whole stack.
+    #[default]
+    #[serde(rename = "NoLimit")]
+    NoLimit,
+    /// At most the size of the pot once the player's call is in.
+    #[serde(rename = "PotLimit")]
+    PotLimit,
+    /// Exactly one fixed increment: the big blind preflop and on the flop, double on the turn
+    /// and river.
+    #[serde(rename = "FixedLimit")]
+    FixedLimit,
+}
+
+impl Variant {
+    /// Number of hole cards dealt to each player.
+    fn hole_cards(&self) -> usize {
+        match self {
+            Variant::Holdem | Variant::ShortDeck => 2,
+            Variant::Omaha => 4,
+            Variant::FiveCardDraw => 5,
+        }
+    }
+
+    /// Number of community cards in a complete board.
+    fn board_cards(&self) -> usize {
+        match self {
+            Variant::FiveCardDraw => 0,
+            _ => 5,
+        }
+    }
+
+    /// The full deck this variant is dealt from.
+    fn deck(&self) -> Vec<Card> {
+        match self {
+            Variant::Holdem | Variant::Omaha | Variant::FiveCardDraw => standard_deck(),
+            Variant::ShortDeck => standard_deck().into_iter().filter(|c| c.value() >= 6).collect(),
+        }
+    }
+
+    /// Evaluates a player's best hand from their hole cards and the board under this variant's rules,
+    /// or `None` when they don't make up a five-card hand.
+    fn best_hand(&self, hole_cards: &[Card], board: &[Card]) -> Option<Hand> {
+        match self {
+            Variant::Holdem | Variant::FiveCardDraw => {
+                let mut cards = hole_cards.to_vec();
+                cards.extend(board.iter().cloned());
+                evaluate_best_hand(&cards)
+            },
+            Variant::ShortDeck => evaluate_best_hand_with(&[hole_cards, board].concat(), evaluate_short_deck_hand),
+            Variant::Omaha => {
+                let board_combos = combinations(board, 3);
+                combinations(hole_cards, 2)
+                    .iter()
+                    .flat_map(|hole| board_combos.iter().map(move |b| [hole.as_slice(), b.as_slice()].concat()))
+                    .map(|cards| evaluate_hand(&cards))
+                    .max()
+            },
+        }
+    }
+
+    /// Like `best_hand`, but also returns the five cards making the hand.
+    fn best_hand_cards(&self, hole_cards: &[Card], board: &[Card]) -> Option<(Hand, Vec<Card>)> {
+        match self {
+            Variant::Holdem | Variant::FiveCardDraw => best_hand_cards(&[hole_cards, board].concat()),
+            Variant::ShortDeck => best_hand_cards_with(&[hole_cards, board].concat(), evaluate_short_deck_hand),
+            Variant::Omaha => {
+                let board_combos = combinations(board, 3);
+                combinations(hole_cards, 2)
+                    .iter()
+                    .flat_map(|hole| board_combos.iter().map(move |b| [hole.as_slice(), b.as_slice()].concat()))
+                    .map(|cards| (evaluate_hand(&cards), cards))
+                    .max_by(|a, b| a.0.cmp(&b.0))
+            },
+        }
+    }
+}
+
+/// How opponents' hole cards are treated when estimating a player's win probability.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum EquityMode {
+    /// Opponents' hands are unknown and re-dealt from the unseen cards in every simulation.
+    #[default]
+    #[serde(rename = "Hidden")]
+    Hidden,
+    /// Opponents' actual hole cards are used, giving the showdown odds for the dealt hands.
+    #[serde(rename = "Exact")]
+    Exact,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Action {
     player_index: usize,
@@ -147,6 +382,10 @@ pub enum ActionType {
     Bet,
     #[serde(rename = "Raise")]
     Raise,
+    #[serde(rename = "Show")]
+    Show,  // Reveal a losing hand at showdown
+    #[serde(rename = "Muck")]
+    Muck,  // Throw a losing hand away unseen at showdown
 }
 
 #[derive(Debug, Deserialize)]
@@ -154,14 +393,533 @@ pub struct NewGameQuery {
     num_players: usize,
     game_mode: GameMode,
     starting_chips: u32,
+    #[serde(default)]
+    equity_mode: EquityMode,
+    #[serde(default)]
+    variant: Variant,
+    #[serde(default)]
+    action_timeout_secs: Option<u64>,
+    #[serde(default = "default_small_blind")]
+    small_blind: u32,
+    #[serde(default = "default_big_blind")]
+    big_blind: u32,
+    #[serde(default = "default_difficulty")]
+    difficulty: f64,
+    #[serde(default)]
+    ante: u32,
+    #[serde(default)]
+    straddle: bool,
+    #[serde(default)]
+    straddles: u32,  // Consecutive straddles, e.g. 2 for a double straddle; `straddle` alone means one
+    #[serde(default = "default_show_probabilities")]
+    show_probabilities: bool,
+    #[serde(default = "default_human_count")]
+    human_count: usize,  // How many leading seats are played by humans
+    #[serde(default)]
+    betting_structure: BettingStructure,
+    #[serde(default)]
+    robot_delay_ms: Option<u64>,  // How long each robot thinks before acting in RobotPlay
+    #[serde(default)]
+    allow_rebuy: bool,
+    #[serde(default)]
+    rebuy_amount: u32,  // The stack a busted human is topped up to
+    #[serde(default)]
+    stacks: Option<Vec<u32>>,  // Per-seat starting chips, overriding `starting_chips`; JSON body only
+    #[serde(default)]
+    time_bank_secs: u32,  // Extra seconds each player may use past the action timeout
+    #[serde(default)]
+    rake_percent: f64,  // Fraction of each showdown pot taken as rake, e.g. 0.05 for 5%
+    #[serde(default)]
+    rake_cap: u32,  // Most rake taken from a single pot; no cap when zero
+    #[serde(default)]
+    player_names: Option<Vec<String>>,  // Per-seat names, overriding the defaults; JSON body only
+    #[serde(default)]
+    seed: Option<u64>,  // Makes the shuffles and robot decisions repeatable
+}
+
+/// Body of `/draw`: the seat drawing and the positions of the cards it throws away.
+#[derive(Debug, Deserialize)]
+pub struct DrawRequest {
+    player_index: usize,
+    #[serde(default)]
+    discard: Vec<usize>,
+}
+
+/// Body of `/new-game-rigged`: the usual game settings plus the full deck to deal from.
+#[derive(Debug, Deserialize)]
+pub struct RiggedGameRequest {
+    #[serde(flatten)]
+    config: NewGameQuery,
+    deck: Vec<String>,  // All 52 cards in the order they're dealt, first card first
+}
+
+fn default_small_blind() -> u32 {
+    5
+}
+
+fn default_big_blind() -> u32 {
+    10
+}
+
+fn default_difficulty() -> f64 {
+    1.0
+}
+
+fn default_show_probabilities() -> bool {
+    true
+}
+
+fn default_human_count() -> usize {
+    1
+}
+
+impl Default for NewGameQuery {
+    fn default() -> Self {
+        NewGameQuery {
+            num_players: 2,
+            game_mode: GameMode::Simulation,
+            starting_chips: 1000,
+            equity_mode: EquityMode::default(),
+            variant: Variant::default(),
+            action_timeout_secs: None,
+            small_blind: default_small_blind(),
+            big_blind: default_big_blind(),
+            difficulty: default_difficulty(),
+            ante: 0,
+            straddle: false,
+            straddles: 0,
+            show_probabilities: default_show_probabilities(),
+            human_count: default_human_count(),
+            betting_structure: BettingStructure::default(),
+            robot_delay_ms: None,
+            allow_rebuy: false,
+            rebuy_amount: 0,
+            stacks: None,
+            time_bank_secs: 0,
+            rake_percent: 0.0,
+            rake_cap: 0,
+            player_names: None,
+            seed: None,
+        }
+    }
+}
+
+/// One row of the session leaderboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    name: String,
+    chips: u32,
+    total_profit: i32,
+    games_won: u32,
+    games_played: u32,
+    win_rate: f64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PlayerAction {
+    #[serde(default)]
+    player_index: usize,  // The acting human's seat; only needed with several humans
     action_type: ActionType,
     amount: Option<u32>,
 }
 
+/// The actions available to a player, with the chip amounts that make each one legal.
+/// Raise amounts are totals for the betting round, matching `Action::amount`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LegalActions {
+    player_index: usize,
+    actions: Vec<ActionType>,
+    call_amount: u32,
+    min_raise: u32,
+    max_raise: u32,
+}
+
+/// One seat as the table shows it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeatView {
+    seat: usize,
+    name: String,
+    chips: u32,
+    current_bet: u32,
+    is_robot: bool,
+    folded: bool,  // Out of the hand, whether by folding or by being busted
+    all_in: bool,
+    to_act: bool,
+}
+
+/// The seating and action order, so clients don't have to work them out from the full state.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableView {
+    seats: Vec<SeatView>,
+    dealer: usize,
+    small_blind_seat: Option<usize>,
+    big_blind_seat: Option<usize>,
+}
+
+/// Why a game operation was refused. Clients receive it as `{ "code": ..., "message": ... }`,
+/// so they can branch on the code rather than match on the message text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError {
+    NoActiveGame,
+    NoSuchSeat,
+    NotHumanSeat,
+    OutOfTurn { waiting_on: String },
+    NotAtShowdown,
+    HandOver,
+    AlreadyShown,
+    IllegalCheck,
+    NothingToCall,
+    AmountRequired { verb: &'static str },
+    AllInOnly { verb: &'static str, amount: u32, all_in: u32 },
+    AllInCallOnly { call: u32 },
+    RaiseTooSmall { verb: &'static str, amount: u32, min: u32 },
+    RaiseTooLarge { verb: &'static str, amount: u32, all_in: u32 },
+    OverLimit { verb: &'static str, amount: u32, max: u32 },
+    BetFacingBet,
+    RaiseWithoutBet,
+    NotEnoughChips,
+    PotOverflow,
+    HandInProgress,
+    GameOver { winner: String },
+    HandStillInPlay,
+    NoRobotToAct,
+    NoStreetToDeal,
+    BettingNotComplete,
+    RebuyNotAllowed,
+    NotBusted,
+    NoSuchHand { index: usize },
+    NoSuchAction { index: usize, recorded: usize },
+    /// Anything without a dedicated code, such as an inconsistent deck.
+    Invalid(String),
+}
+
+impl GameError {
+    /// A stable, machine-readable name for the error.
+    fn code(&self) -> &'static str {
+        match self {
+            GameError::NoActiveGame => "no_active_game",
+            GameError::NoSuchSeat => "no_such_seat",
+            GameError::NotHumanSeat => "not_human_seat",
+            GameError::OutOfTurn { .. } => "out_of_turn",
+            GameError::NotAtShowdown => "not_at_showdown",
+            GameError::HandOver => "hand_over",
+            GameError::AlreadyShown => "already_shown",
+            GameError::IllegalCheck => "illegal_check",
+            GameError::NothingToCall => "nothing_to_call",
+            GameError::AmountRequired { .. } => "amount_required",
+            GameError::AllInOnly { .. } => "all_in_only",
+            GameError::AllInCallOnly { .. } => "all_in_call_only",
+            GameError::RaiseTooSmall { .. } => "raise_too_small",
+            GameError::RaiseTooLarge { .. } => "raise_too_large",
+            GameError::OverLimit { .. } => "over_limit",
+            GameError::BetFacingBet => "bet_facing_bet",
+            GameError::RaiseWithoutBet => "raise_without_bet",
+            GameError::NotEnoughChips => "not_enough_chips",
+            GameError::PotOverflow => "pot_overflow",
+            GameError::HandInProgress => "hand_in_progress",
+            GameError::GameOver { .. } => "game_over",
+            GameError::HandStillInPlay => "hand_still_in_play",
+            GameError::NoRobotToAct => "no_robot_to_act",
+            GameError::NoStreetToDeal => "no_street_to_deal",
+            GameError::BettingNotComplete => "betting_not_complete",
+            GameError::RebuyNotAllowed => "rebuy_not_allowed",
+            GameError::NotBusted => "not_busted",
+            GameError::NoSuchHand { .. } => "no_such_hand",
+            GameError::NoSuchAction { .. } => "no_such_action",
+            GameError::Invalid(_) => "invalid",
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::NoActiveGame => write!(f, "No active game"),
+            GameError::NoSuchSeat => write!(f, "No player in that seat"),
+            GameError::NotHumanSeat => write!(f, "That seat isn't played by a human"),
+            GameError::OutOfTurn { waiting_on } => write!(f, "It's not your turn: waiting on {}", waiting_on),
+            GameError::NotAtShowdown => write!(f, "Cards can only be shown or mucked at showdown"),
+            GameError::HandOver => write!(f, "The hand is over: show or muck at showdown"),
+            GameError::AlreadyShown => write!(f, "Only a hand that hasn't been shown can be shown or mucked"),
+            GameError::IllegalCheck => write!(f, "Cannot check when there's a bet"),
+            GameError::NothingToCall => write!(f, "There is no bet to call"),
+            GameError::AmountRequired { verb } => write!(f, "{} amount required", verb),
+            GameError::AllInOnly { verb, amount, all_in } => {
+                write!(f, "{} to {} is not allowed: you can only go all-in for {}", verb, amount, all_in)
+            },
+            GameError::AllInCallOnly { call } => {
+                write!(f, "Your stack doesn't cover the bet: you can only call all-in for {}", call)
+            },
+            GameError::RaiseTooSmall { verb, amount, min } => {
+                write!(f, "{} to {} is below the minimum of {}", verb, amount, min)
+            },
+            GameError::RaiseTooLarge { verb, amount, all_in } => {
+                write!(f, "{} to {} is more than your stack allows: all-in is {}", verb, amount, all_in)
+            },
+            GameError::OverLimit { verb, amount, max } => {
+                write!(f, "{} to {} is over the betting limit: the most allowed is {}", verb, amount, max)
+            },
+            GameError::BetFacingBet => write!(f, "Cannot bet when there's already a bet, raise instead"),
+            GameError::RaiseWithoutBet => write!(f, "Cannot raise when there's no bet, bet instead"),
+            GameError::NotEnoughChips => write!(f, "Not enough chips"),
+            GameError::PotOverflow => write!(f, "Bet would overflow the pot"),
+            GameError::HandInProgress => write!(f, "The current hand still has chips in the pot"),
+            GameError::GameOver { winner } => write!(f, "Game over: {} holds all the chips", winner),
+            GameError::HandStillInPlay => write!(f, "The hand is still in play"),
+            GameError::NoRobotToAct => write!(f, "No robot is waiting to act"),
+            GameError::NoStreetToDeal => write!(f, "There is no street left to deal in this hand"),
+            GameError::BettingNotComplete => write!(f, "The betting on this street isn't over yet"),
+            GameError::RebuyNotAllowed => write!(f, "Rebuys aren't allowed in this game"),
+            GameError::NotBusted => write!(f, "Only a player with no chips left can rebuy"),
+            GameError::NoSuchHand { index } => write!(f, "No hand at index {}", index),
+            GameError::NoSuchAction { index, recorded } => {
+                write!(f, "No action at index {}: the hand has {} actions", index, recorded)
+            },
+            GameError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl Serialize for GameError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("GameError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+impl From<&str> for GameError {
+    fn from(message: &str) -> Self {
+        GameError::Invalid(message.to_string())
+    }
+}
+
+impl From<String> for GameError {
+    fn from(message: String) -> Self {
+        GameError::Invalid(message)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    since: u64,  // The last version the client has seen
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandHistoryQuery {
+    phase: Option<GamePhase>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpectateQuery {
+    /// Include every player's hole cards, for admin overlays.
+    #[serde(default)]
+    reveal: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BenchmarkQuery {
+    hands: usize,
+}
+
+/// Result of `/benchmark`: how long the deals took and how many hands that is per second.
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    hands: usize,
+    elapsed_ms: f64,
+    hands_per_second: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ShowdownQuery {
+    /// Deal the rest of the board twice when everyone is all-in.
+    #[serde(default)]
+    run_it_twice: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevealProbabilitiesQuery {
+    show: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    hand_index: usize,
+    action_index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProbabilitiesQuery {
+    sims: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebuyQuery {
+    #[serde(default)]
+    player_index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportHandQuery {
+    index: usize,
+}
+
+/// A spot to estimate equity for, outside any running game. Cards use short notation.
+#[derive(Debug, Deserialize)]
+pub struct SimulateRequest {
+    hole_cards: Vec<String>,
+    opponents: usize,
+    #[serde(default)]
+    board: Vec<String>,
+    #[serde(default)]
+    dead_cards: Vec<String>,  // Cards known to be out of play, e.g. folded or exposed
+    sims: usize,
+    #[serde(default)]
+    opponent_range: Option<RangePreset>,  // Deal opponents only hands from this range
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulateResponse {
+    win_probability: f64,
+    standard_error: f64,
+    confidence_interval: (f64, f64),  // 95% bounds on `win_probability`
+}
+
+/// A bluff to price: betting `bet` into `pot`. Supplying hole cards (and optionally a board)
+/// also estimates the bettor's equity against one random hand when called.
+#[derive(Debug, Deserialize)]
+pub struct FoldEquityRequest {
+    bet: u32,
+    pot: u32,
+    #[serde(default)]
+    hole_cards: Vec<String>,
+    #[serde(default)]
+    board: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FoldEquityResponse {
+    required_fold_frequency: f64,  // How often the bet must win the pot uncontested to break even
+    equity_when_called: Option<f64>,
+}
+
+/// Body of `/whatif`: the board to try in place of the real one, in short notation.
+#[derive(Debug, Deserialize)]
+pub struct WhatIfRequest {
+    board: Vec<String>,
+}
+
+/// A Hold'em hand on the flop or turn to count the outs of, in short notation.
+#[derive(Debug, Deserialize)]
+pub struct OutsRequest {
+    hole_cards: Vec<String>,
+    board: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutsResponse {
+    hand_type: HandType,  // What the player holds now
+    outs: usize,
+    cards: Vec<Card>,  // The unseen cards that would improve the hand type
+}
+
+/// Five to seven cards to find the best hand in, in short notation.
+#[derive(Debug, Deserialize)]
+pub struct EvaluateRequest {
+    cards: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvaluateResponse {
+    hand_type: HandType,
+    description: String,
+    cards: Vec<Card>,  // The five cards making the hand
+}
+
+/// Which build of the server is running, as reported by `/version`.
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    version: &'static str,
+    git_hash: &'static str,  // Short commit hash, or "unknown" when built outside a git checkout
+    built_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One hand type in the `/rankings` help list, with an example of it.
+#[derive(Debug, Serialize)]
+pub struct HandRanking {
+    hand_type: HandType,
+    description: &'static str,
+    example: Vec<Card>,
+    example_name: String,  // The example as a dealer would announce it
+}
+
+impl fmt::Display for Card {
+    /// Renders the card in standard short notation, e.g. `Ah`, `Ks`, `Td`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = match self.rank {
+            Rank::Two => '2',
+            Rank::Three => '3',
+            Rank::Four => '4',
+            Rank::Five => '5',
+            Rank::Six => '6',
+            Rank::Seven => '7',
+            Rank::Eight => '8',
+            Rank::Nine => '9',
+            Rank::Ten => 'T',
+            Rank::Jack => 'J',
+            Rank::Queen => 'Q',
+            Rank::King => 'K',
+            Rank::Ace => 'A',
+        };
+        let suit = match self.suit {
+            Suit::Hearts => 'h',
+            Suit::Diamonds => 'd',
+            Suit::Clubs => 'c',
+            Suit::Spades => 's',
+        };
+        write!(f, "{}{}", rank, suit)
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    /// Parses the short notation produced by `Display`, e.g. `Ah` or `Td`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut chars = text.chars();
+        let (Some(rank), Some(suit), None) = (chars.next(), chars.next(), chars.next()) else {
+            return Err(format!("Invalid card '{}'", text));
+        };
+        let rank = match rank {
+            '2' => Rank::Two,
+            '3' => Rank::Three,
+            '4' => Rank::Four,
+            '5' => Rank::Five,
+            '6' => Rank::Six,
+            '7' => Rank::Seven,
+            '8' => Rank::Eight,
+            '9' => Rank::Nine,
+            'T' => Rank::Ten,
+            'J' => Rank::Jack,
+            'Q' => Rank::Queen,
+            'K' => Rank::King,
+            'A' => Rank::Ace,
+            _ => return Err(format!("Invalid rank in card '{}'", text)),
+        };
+        let suit = match suit {
+            'h' => Suit::Hearts,
+            'd' => Suit::Diamonds,
+            'c' => Suit::Clubs,
+            's' => Suit::Spades,
+            _ => return Err(format!("Invalid suit in card '{}'", text)),
+        };
+        Ok(Card { suit, rank })
+    }
+}
+
 impl Card {
     fn value(&self) -> u8 {
         match self.rank {
@@ -185,12 +943,20 @@ impl Card {
 /// Represents a 5-card hand with an evaluation (hand type) and the card values used for tie-breaking.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 struct Hand {
+    category: u8,  // Where the hand type ranks under the variant's rules; compared first
     hand_type: HandType,
     values: Vec<u8>,
 }
 
+impl Hand {
+    /// A hand ranked by the standard order of hand types.
+    fn new(hand_type: HandType, values: Vec<u8>) -> Hand {
+        Hand { category: hand_type.clone() as u8, hand_type, values }
+    }
+}
+
 /// Enumeration of poker hand types.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 enum HandType {
     HighCard,
     Pair,
@@ -201,237 +967,1205 @@ enum HandType {
     FullHouse,
     FourOfAKind,
     StraightFlush,
+    RoyalFlush,
 }
 
-/// Evaluates a 5-card hand.
+/// Evaluates a 5-card hand. The flush and straight checks cover exactly the cards passed, so
+/// anything but five cards is rejected; `evaluate_best_hand` picks the best five from more.
 fn evaluate_hand(cards: &[Card]) -> Hand {
-    let mut values: Vec<u8> = cards.iter().map(|c| c.value()).collect();
-    values.sort_unstable_by(|a, b| b.cmp(a));
+    assert_eq!(cards.len(), 5, "evaluate_hand takes exactly 5 cards");
+    // One bit per value present (bits 2-14), plus per-value and per-suit counts.
+    let mut rank_mask: u16 = 0;
+    let mut counts = [0u8; 15];
+    let mut suit_counts = [0u8; 4];
+    for card in cards {
+        let v = card.value();
+        rank_mask |= 1 << v;
+        counts[v as usize] += 1;
+        suit_counts[card.suit as usize] += 1;
+    }
 
-    // Check flush (all cards have the same suit)
-    let is_flush = cards.iter().all(|c| c.suit == cards[0].suit);
+    // Check flush (all five cards have the same suit)
+    let is_flush = suit_counts.contains(&5);
 
-    // Check straight (sequential values)
-    let mut is_straight = false;
-    if values.windows(2).all(|w| w[0] == w[1] + 1) {
-        is_straight = true;
-    } else if values == vec![14, 5, 4, 3, 2] {
+    // Check straight: five distinct values occupying five adjacent bits
+    const WHEEL_MASK: u16 = (1 << 14) | 0b11_1100;
+    let is_straight = rank_mask.count_ones() == 5
+        && (rank_mask >> rank_mask.trailing_zeros() == 0b1_1111 || rank_mask == WHEEL_MASK);
+    if rank_mask == WHEEL_MASK {
         // Special case for Ace-low straight
-        is_straight = true;
-        values = vec![5, 4, 3, 2, 1];
+        counts[14] = 0;
+        counts[1] = 1;
     }
 
-    // Count frequencies of card values
-    let mut freq = std::collections::HashMap::new();
-    for &v in &values {
-        *freq.entry(v).or_insert(0) += 1;
+    // Group equal values (1 is the ace in an ace-low straight)
+    let mut groups = [(0u8, 0u8); 5];
+    let mut num_groups = 0;
+    for v in (1..=14u8).rev() {
+        if counts[v as usize] > 0 {
+            groups[num_groups] = (v, counts[v as usize]);
+            num_groups += 1;
+        }
     }
-    let mut freq_vec: Vec<_> = freq.into_iter().collect();
-    freq_vec.sort_by_key(|&(v, count)| (-(count as i32), -(v as i32)));
+    let groups = &mut groups[..num_groups];
+    groups.sort_unstable_by_key(|&(v, count)| (std::cmp::Reverse(count), std::cmp::Reverse(v)));
+    let group_size = |i: usize| groups.get(i).map_or(0, |&(_, count)| count);
 
-    let hand_type = if is_flush && is_straight {
+    // Order the tie-break values by group size, then rank: trips before the pair in a full house,
+    // both pairs before the kicker in two pair, and so on.
+    let values: Vec<u8> = groups
+        .iter()
+        .flat_map(|&(v, count)| std::iter::repeat_n(v, count as usize))
+        .collect();
+
+    let hand_type = if is_flush && is_straight && values[0] == 14 {
+        HandType::RoyalFlush
+    } else if is_flush && is_straight {
         HandType::StraightFlush
-    } else if freq_vec[0].1 == 4 {
+    } else if group_size(0) == 4 {
         HandType::FourOfAKind
-    } else if freq_vec[0].1 == 3 && freq_vec.get(1).map_or(0, |&(_, c)| c) == 2 {
+    } else if group_size(0) == 3 && group_size(1) == 2 {
         HandType::FullHouse
     } else if is_flush {
         HandType::Flush
     } else if is_straight {
         HandType::Straight
-    } else if freq_vec[0].1 == 3 {
+    } else if group_size(0) == 3 {
         HandType::ThreeOfAKind
-    } else if freq_vec[0].1 == 2 && freq_vec.get(1).map_or(0, |&(_, c)| c) == 2 {
+    } else if group_size(0) == 2 && group_size(1) == 2 {
         HandType::TwoPair
-    } else if freq_vec[0].1 == 2 {
+    } else if group_size(0) == 2 {
         HandType::Pair
     } else {
         HandType::HighCard
     };
 
-    Hand { hand_type, values }
+    Hand::new(hand_type, values)
+}
+
+/// Evaluates a 5-card short-deck hand: A-6-7-8-9 is a nine-high straight, and a flush outranks
+/// a full house since it's the rarer hand with four ranks removed.
+fn evaluate_short_deck_hand(cards: &[Card]) -> Hand {
+    let hand = evaluate_hand(cards);
+    let mut values: Vec<u8> = cards.iter().map(|c| c.value()).collect();
+    values.sort_unstable_by(|a, b| b.cmp(a));
+    let hand = if values == [14, 9, 8, 7, 6] {
+        // The ace plays low, below the six.
+        let hand_type = if hand.hand_type == HandType::Flush { HandType::StraightFlush } else { HandType::Straight };
+        Hand::new(hand_type, vec![9, 8, 7, 6, 1])
+    } else {
+        hand
+    };
+    let category = match hand.hand_type {
+        HandType::Flush => HandType::FullHouse as u8,
+        HandType::FullHouse => HandType::Flush as u8,
+        _ => hand.category,
+    };
+    Hand { category, ..hand }
+}
+
+/// The name of a card value, singular or plural: "Ace" / "Aces", "Six" / "Sixes".
+fn value_name(value: u8, plural: bool) -> String {
+    let name = match value {
+        2 => "Two",
+        3 => "Three",
+        4 => "Four",
+        5 => "Five",
+        6 => "Six",
+        7 => "Seven",
+        8 => "Eight",
+        9 => "Nine",
+        10 => "Ten",
+        11 => "Jack",
+        12 => "Queen",
+        13 => "King",
+        _ => "Ace",  // 14, or 1 in an ace-low straight
+    };
+    match (plural, value) {
+        (false, _) => name.to_string(),
+        (true, 6) => "Sixes".to_string(),
+        (true, _) => format!("{}s", name),
+    }
+}
+
+/// Describes a hand the way a dealer would announce it, e.g. "Full house, Kings full of Twos".
+fn describe_hand(hand: &Hand) -> String {
+    let v = &hand.values;
+    match hand.hand_type {
+        HandType::HighCard => format!("{} high", value_name(v[0], false)),
+        HandType::Pair => format!("Pair of {}", value_name(v[0], true)),
+        HandType::TwoPair => format!("Two pair, {} and {}", value_name(v[0], true), value_name(v[2], true)),
+        HandType::ThreeOfAKind => format!("Three of a kind, {}", value_name(v[0], true)),
+        HandType::Straight => format!("Straight, {} high", value_name(v[0], false)),
+        HandType::Flush => format!("Flush, {} high", value_name(v[0], false)),
+        HandType::FullHouse => format!("Full house, {} full of {}", value_name(v[0], true), value_name(v[3], true)),
+        HandType::FourOfAKind => format!("Four of a kind, {}", value_name(v[0], true)),
+        HandType::StraightFlush => format!("Straight flush, {} high", value_name(v[0], false)),
+        HandType::RoyalFlush => "Royal flush".to_string(),
+    }
+}
+
+/// Every hand type from weakest to strongest, each with what makes it and an example.
+fn hand_rankings() -> Vec<HandRanking> {
+    [
+        (HandType::HighCard, "No two cards of a rank, no straight and no flush", "Ah Jd 8c 5s 2h"),
+        (HandType::Pair, "Two cards of the same rank", "Kh Kd 9c 6s 3h"),
+        (HandType::TwoPair, "Two different pairs", "Qh Qd 7c 7s 4h"),
+        (HandType::ThreeOfAKind, "Three cards of the same rank", "Jh Jd Jc 8s 2h"),
+        (HandType::Straight, "Five cards in sequence", "9h 8d 7c 6s 5h"),
+        (HandType::Flush, "Five cards of the same suit", "Ah Jh 9h 6h 3h"),
+        (HandType::FullHouse, "Three of a kind with a pair", "Th Td Tc 4s 4h"),
+        (HandType::FourOfAKind, "All four cards of a rank", "8h 8d 8c 8s Kh"),
+        (HandType::StraightFlush, "Five cards in sequence, all of the same suit", "9s 8s 7s 6s 5s"),
+        (HandType::RoyalFlush, "Ace-high straight flush", "As Ks Qs Js Ts"),
+    ]
+    .into_iter()
+    .map(|(hand_type, description, example)| {
+        let example: Vec<Card> = example
+            .split_whitespace()
+            .map(|card| card.parse().expect("ranking examples are valid cards"))
+            .collect();
+        HandRanking { hand_type, description, example_name: describe_hand(&evaluate_hand(&example)), example }
+    })
+    .collect()
+}
+
+/// Advances `indices`, a strictly increasing selection from `0..n`, to the next combination
+/// in lexicographic order. Returns `false` once the last combination has been passed.
+fn next_combination(indices: &mut [usize], n: usize) -> bool {
+    let k = indices.len();
+    // Find the rightmost index that can still move right, bump it, and pack the rest after it.
+    for i in (0..k).rev() {
+        if indices[i] < n - k + i {
+            indices[i] += 1;
+            for j in i + 1..k {
+                indices[j] = indices[j - 1] + 1;
+            }
+            return true;
+        }
+    }
+    false
 }
 
 /// Generates all combinations of `k` items from a slice.
 fn combinations<T: Clone>(items: &[T], k: usize) -> Vec<Vec<T>> {
-    if k == 0 {
-        return vec![vec![]];
-    }
     if items.len() < k {
         return vec![];
     }
 
-    let mut result = Vec::new();
-    // For each index, combine the current item with all combinations of the remaining items.
-    for (i, item) in items.iter().enumerate() {
-        let rest_combos = combinations(&items[i + 1..], k - 1);
-        for mut combo in rest_combos {
-            let mut new_combo = vec![item.clone()];
-            new_combo.append(&mut combo);
-            result.push(new_combo);
+    let mut result = Vec::with_capacity(n_choose_k(items.len(), k));
+    let mut indices: Vec<usize> = (0..k).collect();
+    loop {
+        result.push(indices.iter().map(|&i| items[i].clone()).collect());
+        if !next_combination(&mut indices, items.len()) {
+            return result;
         }
     }
-    result
 }
 
-/// Evaluates the best possible 5-card hand out of a collection of cards.
-fn evaluate_best_hand(cards: &[Card]) -> Hand {
-    assert!(cards.len() >= 5, "At least 5 cards are required to evaluate a hand");
+/// Evaluates the best possible 5-card hand out of a collection of cards, or `None` when there are
+/// fewer than five of them, as for a folded player's empty hand.
+fn evaluate_best_hand(cards: &[Card]) -> Option<Hand> {
+    evaluate_best_hand_with(cards, evaluate_hand)
+}
+
+/// Evaluates the best 5-card hand out of a collection of cards, scoring each hand with `evaluate`.
+fn evaluate_best_hand_with(cards: &[Card], evaluate: impl Fn(&[Card]) -> Hand) -> Option<Hand> {
+    if cards.len() < 5 {
+        return None;
+    }
     if cards.len() == 5 {
-        return evaluate_hand(cards);
+        return Some(evaluate(cards));
     }
-    combinations(cards, 5)
-        .into_iter()
-        .map(|combo| evaluate_hand(&combo))
-        .max()
-        .unwrap()
+    // Walk the 5-card selections in place rather than materialising them all.
+    let mut indices = [0, 1, 2, 3, 4];
+    let mut best = evaluate(&indices.map(|i| cards[i]));
+    while next_combination(&mut indices, cards.len()) {
+        let hand = evaluate(&indices.map(|i| cards[i]));
+        if hand > best {
+            best = hand;
+        }
+    }
+    Some(best)
 }
 
-/// Simulates the win probability of a player's hand against opponents using Monte Carlo simulation.
-/// It completes the community board with cards drawn from the remaining deck, then
-/// evaluates every player's best hand and awards the win fraction when a tie occurs.
-fn simulate_win_probability(
+/// Like `evaluate_best_hand`, but also returns the five cards making the best hand.
+fn best_hand_cards(cards: &[Card]) -> Option<(Hand, Vec<Card>)> {
+    best_hand_cards_with(cards, evaluate_hand)
+}
+
+/// Like `evaluate_best_hand_with`, but also returns the five cards making the best hand.
+fn best_hand_cards_with(cards: &[Card], evaluate: impl Fn(&[Card]) -> Hand) -> Option<(Hand, Vec<Card>)> {
+    if cards.len() < 5 {
+        return None;
+    }
+    let mut indices = [0, 1, 2, 3, 4];
+    let mut best_indices = indices;
+    let mut best = evaluate(&indices.map(|i| cards[i]));
+    while next_combination(&mut indices, cards.len()) {
+        let hand = evaluate(&indices.map(|i| cards[i]));
+        if hand > best {
+            best = hand;
+            best_indices = indices;
+        }
+    }
+    Some((best, best_indices.iter().map(|&i| cards[i]).collect()))
+}
+
+/// Scores a single completed board: returns the player's share of the pot (1.0 for an outright win,
+/// a fraction on a tie, 0.0 on a loss).
+fn showdown_share(
     player_cards: &[Card],
     other_players_cards: &[Vec<Card>],
-    community_cards: &[Card],
-    remaining_deck: &[Card],
-    num_simulations: usize,
+    final_board: &[Card],
+    variant: Variant,
 ) -> f64 {
-    // If there are no opponents, the win probability is 100%.
-    if other_players_cards.is_empty() {
-        return 1.0;
-    }
+    // Evaluate best hand for the player; without one they can't win anything.
+    let Some(player_best) = variant.best_hand(player_cards, final_board) else { return 0.0 };
+
+    // Evaluate each opponent's best hand, skipping any who can't form one.
+    let mut all_hands = vec![player_best.clone()];
+    all_hands.extend(other_players_cards.iter().filter_map(|other| variant.best_hand(other, final_board)));
 
-    let total_needed = 5usize.saturating_sub(community_cards.len());
-    if remaining_deck.len() < total_needed {
-        return 1.0 / (other_players_cards.len() as f64 + 1.0);
+    // Identify the maximum hand and count how many players achieved it.
+    if let Some(max_hand) = all_hands.iter().max() {
+        let tie_count = all_hands.iter().filter(|&hand| hand == max_hand).count() as f64;
+        if player_best == *max_hand {
+            return 1.0 / tie_count;
+        }
     }
+    0.0
+}
 
-    let mut total_win = 0.0;
-    let mut rng = rand::thread_rng();
+/// Boards with at most this many possible completions are enumerated instead of sampled.
+const MAX_ENUMERATED_BOARDS: usize = 1000;
 
-    for _ in 0..num_simulations {
-        let mut sim_deck = remaining_deck.to_vec();
-        sim_deck.shuffle(&mut rng);
+/// How a hand fares over the simulated boards: the fractions it wins outright, ties and loses,
+/// which sum to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+struct EquityBreakdown {
+    win: f64,
+    tie: f64,
+    lose: f64,
+    /// The average share of the pot won on the tied boards, weighted like `tie`.
+    #[serde(skip)]
+    tie_share: f64,
+    /// Sampling error of `pot_share`; zero when every board was enumerated.
+    standard_error: f64,
+}
+
+/// A contender's `EquityBreakdown` over the rest of an all-in board.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SeatEquity {
+    seat: usize,
+    #[serde(flatten)]
+    equity: EquityBreakdown,
+}
+
+impl EquityBreakdown {
+    /// Expected share of the pot: outright wins plus the split pots.
+    fn pot_share(&self) -> f64 {
+        self.win + self.tie_share
+    }
+}
+
+/// The standard error of a probability `p` estimated from `samples` trials, from the binomial
+/// variance p(1 - p) / n. Split pots make the real variance a little smaller.
+fn standard_error(p: f64, samples: usize) -> f64 {
+    if samples == 0 {
+        return 0.0;
+    }
+    (p * (1.0 - p) / samples as f64).sqrt()
+}
+
+/// The 95% confidence interval around a probability `p`, kept within [0, 1].
+fn confidence_interval_95(p: f64, standard_error: f64) -> (f64, f64) {
+    ((p - 1.96 * standard_error).max(0.0), (p + 1.96 * standard_error).min(1.0))
+}
+
+/// Simulates the win probability of a player's hand against opponents using Monte Carlo simulation.
+/// It completes the community board with cards drawn from the remaining deck, then
+/// evaluates every player's best hand and awards the win fraction when a tie occurs.
+/// `dead_cards` are known to be out of play and never drawn. With `continue_probabilities`, each
+/// opponent only stays in to contest the pot with their entry's chance, so players yet to act
+/// may fold first; the result is then the chance of winning the hand rather than the showdown.
+#[allow(clippy::too_many_arguments)]
+fn simulate_win_probability(
+    player_cards: &[Card],
+    other_players_cards: &[Vec<Card>],
+    continue_probabilities: Option<&[f64]>,
+    community_cards: &[Card],
+    remaining_deck: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+    variant: Variant,
+) -> f64 {
+    simulate_win_probability_with(
+        player_cards,
+        other_players_cards,
+        continue_probabilities,
+        community_cards,
+        remaining_deck,
+        dead_cards,
+        num_simulations,
+        variant,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// Like `simulate_win_probability`, but drawing its randomness from `rng`.
+#[allow(clippy::too_many_arguments)]
+fn simulate_win_probability_with(
+    player_cards: &[Card],
+    other_players_cards: &[Vec<Card>],
+    continue_probabilities: Option<&[f64]>,
+    community_cards: &[Card],
+    remaining_deck: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+    variant: Variant,
+    rng: &mut impl Rng,
+) -> f64 {
+    let everyone_continues = vec![1.0; other_players_cards.len()];
+    simulate_equity_breakdown_with_folds(
+        player_cards,
+        other_players_cards,
+        continue_probabilities.unwrap_or(&everyone_continues),
+        community_cards,
+        remaining_deck,
+        dead_cards,
+        num_simulations,
+        variant,
+        rng,
+    )
+    .pot_share()
+}
+
+/// Like `simulate_win_probability`, but counts outright wins, ties and losses separately.
+fn simulate_equity_breakdown(
+    player_cards: &[Card],
+    other_players_cards: &[Vec<Card>],
+    community_cards: &[Card],
+    remaining_deck: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+    variant: Variant,
+) -> EquityBreakdown {
+    simulate_equity_breakdown_with_folds(
+        player_cards,
+        other_players_cards,
+        &vec![1.0; other_players_cards.len()],
+        community_cards,
+        remaining_deck,
+        dead_cards,
+        num_simulations,
+        variant,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// Like `simulate_equity_breakdown`, folding each opponent before the runout unless they continue
+/// with their `continue_probabilities` entry. A pot nobody contests is won outright. The boards
+/// and folds are drawn from `rng`.
+#[allow(clippy::too_many_arguments)]
+fn simulate_equity_breakdown_with_folds(
+    player_cards: &[Card],
+    other_players_cards: &[Vec<Card>],
+    continue_probabilities: &[f64],
+    community_cards: &[Card],
+    remaining_deck: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+    variant: Variant,
+    rng: &mut impl Rng,
+) -> EquityBreakdown {
+    assert_eq!(continue_probabilities.len(), other_players_cards.len(), "one continue probability per opponent");
+    // If there are no opponents, the hand always wins.
+    if other_players_cards.is_empty() {
+        return EquityBreakdown { win: 1.0, ..Default::default() };
+    }
+
+    let live_deck = without_dead_cards(remaining_deck, dead_cards);
+    let remaining_deck = live_deck.as_slice();
+
+    let total_needed = variant.board_cards().saturating_sub(community_cards.len());
+    // With no boards to sample, every hand gets an even share rather than 0 / 0.
+    if remaining_deck.len() < total_needed || num_simulations == 0 {
+        return EquityBreakdown {
+            tie: 1.0,
+            tie_share: 1.0 / (other_players_cards.len() as f64 + 1.0),
+            ..Default::default()
+        };
+    }
+
+    let (mut wins, mut ties, mut losses, mut tie_share) = (0usize, 0usize, 0usize, 0.0);
+    let everyone_continues = continue_probabilities.iter().all(|&p| p >= 1.0);
+
+    for _ in 0..num_simulations {
+        let mut sim_deck = remaining_deck.to_vec();
+        sim_deck.shuffle(rng);
 
         // Complete the community board.
         let mut final_board = community_cards.to_vec();
         final_board.extend(sim_deck.into_iter().take(total_needed));
 
-        // Evaluate best hand for the player.
-        let mut player_and_board = player_cards.to_vec();
-        player_and_board.extend(final_board.iter().cloned());
-        let player_best = evaluate_best_hand(&player_and_board);
+        let share = if everyone_continues {
+            showdown_share(player_cards, other_players_cards, &final_board, variant)
+        } else {
+            let contesting: Vec<Vec<Card>> = other_players_cards
+                .iter()
+                .zip(continue_probabilities)
+                .filter(|&(_, &p)| rng.gen::<f64>() < p)
+                .map(|(cards, _)| cards.clone())
+                .collect();
+            showdown_share(player_cards, &contesting, &final_board, variant)
+        };
+        if share >= 1.0 {
+            wins += 1;
+        } else if share > 0.0 {
+            ties += 1;
+            tie_share += share;
+        } else {
+            losses += 1;
+        }
+    }
+
+    let total = num_simulations as f64;
+    let win = wins as f64 / total;
+    let tie_share = tie_share / total;
+    EquityBreakdown {
+        win,
+        tie: ties as f64 / total,
+        lose: losses as f64 / total,
+        tie_share,
+        standard_error: standard_error(win + tie_share, num_simulations),
+    }
+}
+
+/// The cards of `deck` that aren't in `dead_cards`.
+fn without_dead_cards(deck: &[Card], dead_cards: &[Card]) -> Vec<Card> {
+    deck.iter().filter(|c| !dead_cards.contains(c)).copied().collect()
+}
+
+/// Number of ways to choose `k` items from `n`.
+fn n_choose_k(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+/// Computes the exact win probability of a player's hand against known opponent hands by
+/// enumerating every possible completion of the community board from the remaining deck.
+/// Unlike the Monte Carlo estimate, the result is exact and identical across calls.
+fn exact_equity(
+    player_cards: &[Card],
+    other_players_cards: &[Vec<Card>],
+    community_cards: &[Card],
+    remaining_deck: &[Card],
+    variant: Variant,
+) -> f64 {
+    exact_equity_breakdown(player_cards, other_players_cards, community_cards, remaining_deck, variant).pot_share()
+}
+
+/// Like `exact_equity`, but counts outright wins, ties and losses separately.
+fn exact_equity_breakdown(
+    player_cards: &[Card],
+    other_players_cards: &[Vec<Card>],
+    community_cards: &[Card],
+    remaining_deck: &[Card],
+    variant: Variant,
+) -> EquityBreakdown {
+    if other_players_cards.is_empty() {
+        return EquityBreakdown { win: 1.0, ..Default::default() };
+    }
+
+    let total_needed = variant.board_cards().saturating_sub(community_cards.len());
+    let completions = combinations(remaining_deck, total_needed);
+    if completions.is_empty() {
+        return EquityBreakdown {
+            tie: 1.0,
+            tie_share: 1.0 / (other_players_cards.len() as f64 + 1.0),
+            ..Default::default()
+        };
+    }
+
+    let (mut wins, mut ties, mut losses, mut tie_share) = (0usize, 0usize, 0usize, 0.0);
+    for completion in &completions {
+        let mut final_board = community_cards.to_vec();
+        final_board.extend(completion.iter().cloned());
+        let share = showdown_share(player_cards, other_players_cards, &final_board, variant);
+        if share >= 1.0 {
+            wins += 1;
+        } else if share > 0.0 {
+            ties += 1;
+            tie_share += share;
+        } else {
+            losses += 1;
+        }
+    }
+
+    let total = completions.len() as f64;
+    EquityBreakdown {
+        win: wins as f64 / total,
+        tie: ties as f64 / total,
+        lose: losses as f64 / total,
+        tie_share: tie_share / total,
+        standard_error: 0.0,
+    }
+}
+
+/// Simulates the win probability of a player's hand against opponents whose hole cards are unknown.
+/// Every simulation deals each opponent a random hand from the unseen cards before completing the
+/// board, so the result is the equity against random holdings rather than the actual hands.
+/// `dead_cards` are known to be out of play and never dealt.
+fn simulate_win_probability_hidden(
+    player_cards: &[Card],
+    num_opponents: usize,
+    community_cards: &[Card],
+    unseen_cards: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+    variant: Variant,
+) -> f64 {
+    simulate_win_probability_hidden_with(
+        player_cards,
+        num_opponents,
+        community_cards,
+        unseen_cards,
+        dead_cards,
+        num_simulations,
+        variant,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// Like `simulate_win_probability_hidden`, but drawing its randomness from `rng`.
+#[allow(clippy::too_many_arguments)]
+fn simulate_win_probability_hidden_with(
+    player_cards: &[Card],
+    num_opponents: usize,
+    community_cards: &[Card],
+    unseen_cards: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+    variant: Variant,
+    rng: &mut impl Rng,
+) -> f64 {
+    if num_opponents == 0 {
+        return 1.0;
+    }
+
+    let live_cards = without_dead_cards(unseen_cards, dead_cards);
+    let unseen_cards = live_cards.as_slice();
+
+    let hole_size = player_cards.len();
+    let board_needed = variant.board_cards().saturating_sub(community_cards.len());
+    if unseen_cards.len() < num_opponents * hole_size + board_needed || num_simulations == 0 {
+        return 1.0 / (num_opponents as f64 + 1.0);
+    }
+
+    let mut total_win = 0.0;
+    for _ in 0..num_simulations {
+        let mut sim_deck = unseen_cards.to_vec();
+        sim_deck.shuffle(rng);
+
+        // Deal each opponent a random hand, then complete the community board.
+        let opponents: Vec<Vec<Card>> = (0..num_opponents)
+            .map(|_| sim_deck.split_off(sim_deck.len() - hole_size))
+            .collect();
+        let mut final_board = community_cards.to_vec();
+        final_board.extend(sim_deck.into_iter().take(board_needed));
+
+        total_win += showdown_share(player_cards, &opponents, &final_board, variant);
+    }
+
+    total_win / num_simulations as f64
+}
+
+/// Preset opponent ranges: the strongest given percentage of Hold'em starting hands.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RangePreset {
+    Top5,
+    Top10,
+    Top25,
+    Top50,
+    Any,
+}
 
-        // Evaluate each opponent's best hand.
-        let mut all_hands = vec![player_best.clone()];
-        for other in other_players_cards {
-            let mut other_and_board = other.clone();
-            other_and_board.extend(final_board.iter().cloned());
-            let other_best = evaluate_best_hand(&other_and_board);
-            all_hands.push(other_best);
+impl RangePreset {
+    fn percent(&self) -> f64 {
+        match self {
+            RangePreset::Top5 => 5.0,
+            RangePreset::Top10 => 10.0,
+            RangePreset::Top25 => 25.0,
+            RangePreset::Top50 => 50.0,
+            RangePreset::Any => 100.0,
         }
+    }
+}
+
+/// A set of Hold'em starting hands an opponent might hold, as individual two-card combos.
+#[derive(Debug, Clone, PartialEq)]
+struct Range {
+    combos: Vec<[Card; 2]>,
+}
+
+impl Range {
+    /// The strongest `percent` of the 1326 starting combos, ranked by `starting_hand_score`.
+    /// Hands are taken whole (every suit combination of AKs, say), so the range may run a
+    /// little over.
+    fn top_percent(percent: f64) -> Range {
+        let target = (percent.clamp(0.0, 100.0) / 100.0 * 1326.0).ceil() as usize;
+        let mut combos: Vec<[Card; 2]> = combinations(&standard_deck(), 2).into_iter().map(|c| [c[0], c[1]]).collect();
+        // Strongest first; equal scores are ordered by card values so the ranking is fixed.
+        let key = |c: &[Card; 2]| {
+            let (high, low) = (c[0].value().max(c[1].value()), c[0].value().min(c[1].value()));
+            (starting_hand_score(c), high, low, c[0].suit == c[1].suit)
+        };
+        combos.sort_by(|a, b| key(b).partial_cmp(&key(a)).expect("scores are finite"));
 
-        // Identify the maximum hand and count how many players achieved it.
-        if let Some(max_hand) = all_hands.iter().max() {
-            let tie_count = all_hands.iter().filter(|&hand| hand == max_hand).count() as f64;
-            if player_best == *max_hand {
-                total_win += 1.0 / tie_count;
+        let mut taken = 0;
+        while taken < target.min(combos.len()) {
+            // Finish the starting hand the last combo belongs to.
+            let hand = key(&combos[taken]);
+            while taken < combos.len() && key(&combos[taken]) == hand {
+                taken += 1;
             }
         }
+        combos.truncate(taken);
+        Range { combos }
     }
 
-    total_win / num_simulations as f64
+    fn preset(preset: RangePreset) -> Range {
+        Range::top_percent(preset.percent())
+    }
+}
+
+/// Scores a Hold'em starting hand with the Chen formula: the high card's points, doubled for a
+/// pair, plus a bonus for suited cards and a penalty for gaps between them.
+fn starting_hand_score(hole_cards: &[Card; 2]) -> f64 {
+    let (a, b) = (hole_cards[0].value(), hole_cards[1].value());
+    let (high, low) = (a.max(b), a.min(b));
+    let points = |v: u8| match v {
+        14 => 10.0,
+        13 => 8.0,
+        12 => 7.0,
+        11 => 6.0,
+        v => f64::from(v) / 2.0,
+    };
+    if high == low {
+        return (points(high) * 2.0).max(5.0);
+    }
+    let mut score = points(high);
+    if hole_cards[0].suit == hole_cards[1].suit {
+        score += 2.0;
+    }
+    let gap = high - low - 1;
+    score -= match gap {
+        0 => 0.0,
+        1 => 1.0,
+        2 => 2.0,
+        3 => 4.0,
+        _ => 5.0,
+    };
+    if gap <= 1 && high < 12 {
+        score += 1.0;
+    }
+    score
+}
+
+/// Like `simulate_win_probability_hidden` for Hold'em, but each opponent is dealt a hand from
+/// their range instead of any two unseen cards. Combos that clash with the known cards or with
+/// another opponent's hand are skipped, and a deal with no combo left for some opponent is
+/// dropped, so alongside the win probability it returns how many simulations were completed.
+fn simulate_win_probability_against_ranges(
+    player_cards: &[Card],
+    ranges: &[Range],
+    community_cards: &[Card],
+    unseen_cards: &[Card],
+    dead_cards: &[Card],
+    num_simulations: usize,
+) -> (f64, usize) {
+    if ranges.is_empty() {
+        return (1.0, 0);
+    }
+
+    let live_cards = without_dead_cards(unseen_cards, dead_cards);
+    // Only combos made entirely of live cards can be dealt.
+    let live_ranges: Vec<Vec<[Card; 2]>> = ranges
+        .iter()
+        .map(|r| r.combos.iter().filter(|c| c.iter().all(|x| live_cards.contains(x))).copied().collect())
+        .collect();
+    let board_needed = 5usize.saturating_sub(community_cards.len());
+    if live_ranges.iter().any(|r| r.is_empty()) || live_cards.len() < ranges.len() * 2 + board_needed {
+        return (1.0 / (ranges.len() as f64 + 1.0), 0);
+    }
+
+    let mut total_win = 0.0;
+    let mut completed = 0;
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..num_simulations {
+        let mut opponents: Vec<Vec<Card>> = Vec::with_capacity(ranges.len());
+        for range in &live_ranges {
+            let open: Vec<&[Card; 2]> = range
+                .iter()
+                .filter(|c| !opponents.iter().flatten().any(|x| c.contains(x)))
+                .collect();
+            match open.choose(&mut rng) {
+                Some(combo) => opponents.push(combo.to_vec()),
+                None => break,
+            }
+        }
+        // Every hand this opponent could hold is already out; the deal is impossible.
+        if opponents.len() < ranges.len() {
+            continue;
+        }
+
+        let mut sim_deck: Vec<Card> = live_cards
+            .iter()
+            .filter(|c| !opponents.iter().flatten().any(|x| x == *c))
+            .copied()
+            .collect();
+        sim_deck.shuffle(&mut rng);
+        let mut final_board = community_cards.to_vec();
+        final_board.extend(sim_deck.into_iter().take(board_needed));
+
+        total_win += showdown_share(player_cards, &opponents, &final_board, Variant::Holdem);
+        completed += 1;
+    }
+
+    if completed == 0 {
+        return (1.0 / (ranges.len() as f64 + 1.0), 0);
+    }
+    (total_win / completed as f64, completed)
+}
+
+/// Builds a full 52-card deck in suit-then-rank order. Every deck in the game starts here.
+fn standard_deck() -> Vec<Card> {
+    let mut deck = Vec::with_capacity(52);
+    for &suit in &[Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades] {
+        for &rank in &[
+            Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
+            Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King, Rank::Ace,
+        ] {
+            deck.push(Card { suit, rank });
+        }
+    }
+    deck
+}
+
+/// Builds `variant`'s full deck shuffled with `rng`.
+fn shuffled_deck(variant: Variant, rng: &mut impl Rng) -> Vec<Card> {
+    let mut deck = variant.deck();
+    deck.shuffle(rng);
+    deck
+}
+
+/// Errors on the first card that appears more than once.
+fn check_no_duplicate_cards<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for card in cards {
+        if !seen.insert(*card) {
+            return Err(format!("Duplicate card {}", card));
+        }
+    }
+    Ok(())
+}
+
+/// The share of the final pot `price` makes up when putting it into `pot`: the equity a call
+/// needs to break even, and equally how often a bet of `price` must take the pot down.
+fn pot_odds(price: u32, pot: u32) -> f64 {
+    f64::from(price) / (f64::from(pot) + f64::from(price))
+}
+
+/// Simulations used to estimate a bluff's equity when it's called.
+const FOLD_EQUITY_SIMULATIONS: usize = 2000;
+
+/// Prices the bluff in a `/fold-equity` request.
+fn fold_equity_spot(request: &FoldEquityRequest) -> Result<FoldEquityResponse, String> {
+    if request.bet == 0 {
+        return Err("Bet must be greater than 0".to_string());
+    }
+    let equity_when_called = if request.hole_cards.is_empty() {
+        None
+    } else {
+        Some(simulate_spot(&SimulateRequest {
+            hole_cards: request.hole_cards.clone(),
+            opponents: 1,
+            board: request.board.clone(),
+            dead_cards: Vec::new(),
+            sims: FOLD_EQUITY_SIMULATIONS,
+            opponent_range: None,
+        })?
+        .0)
+    };
+    Ok(FoldEquityResponse { required_fold_frequency: pot_odds(request.bet, request.pot), equity_when_called })
+}
+
+/// Finds the best hand in the request's cards for `/evaluate`.
+fn evaluate_spot(request: &EvaluateRequest) -> Result<EvaluateResponse, String> {
+    let cards = request.cards.iter().map(|t| t.parse::<Card>()).collect::<Result<Vec<_>, _>>()?;
+    if !(5..=7).contains(&cards.len()) {
+        return Err(format!("Between 5 and 7 cards are required, got {}", cards.len()));
+    }
+    check_no_duplicate_cards(&cards)?;
+
+    let (hand, cards) = best_hand_cards(&cards).ok_or("At least 5 cards are required")?;
+    Ok(EvaluateResponse {
+        hand_type: hand.hand_type.clone(),
+        description: describe_hand(&hand),
+        cards,
+    })
+}
+
+/// Simulations used for the players' win probabilities unless a recompute asks for more.
+const DEFAULT_EQUITY_SIMULATIONS: usize = 1000;
+
+/// Counts the outs in an `/cards/remaining-outs` request: every unseen card that would lift the
+/// player to a better hand type on the next street.
+fn outs_spot(request: &OutsRequest) -> Result<OutsResponse, String> {
+    let parse = |texts: &[String]| texts.iter().map(|t| t.parse::<Card>()).collect::<Result<Vec<_>, _>>();
+    let hole_cards = parse(&request.hole_cards)?;
+    let board = parse(&request.board)?;
+    if hole_cards.len() != Variant::Holdem.hole_cards() {
+        return Err("Exactly 2 hole cards are required".to_string());
+    }
+    if ![3, 4].contains(&board.len()) {
+        return Err("The board must have 3 or 4 cards".to_string());
+    }
+    let known: Vec<Card> = hole_cards.iter().chain(&board).copied().collect();
+    check_no_duplicate_cards(&known)?;
+
+    let current = evaluate_best_hand(&known).ok_or("At least 5 cards are required")?.hand_type;
+    let mut with_next = known.clone();
+    let cards: Vec<Card> = standard_deck()
+        .into_iter()
+        .filter(|c| !known.contains(c))
+        .filter(|&c| {
+            with_next.truncate(known.len());
+            with_next.push(c);
+            evaluate_best_hand(&with_next).is_some_and(|hand| hand.hand_type > current)
+        })
+        .collect();
+    Ok(OutsResponse { hand_type: current, outs: cards.len(), cards })
+}
+
+/// Most simulations a single `/simulate` request may ask for.
+const MAX_SIMULATIONS: usize = 100_000;
+
+/// Estimates a Hold'em hand's equity against `opponents` random hands, dealing the rest of the
+/// board from whatever the request hasn't already used. Returns the equity and the number of
+/// simulations it rests on, which can fall short of `sims` against opponent ranges.
+fn simulate_spot(request: &SimulateRequest) -> Result<(f64, usize), String> {
+    let parse = |texts: &[String]| texts.iter().map(|t| t.parse::<Card>()).collect::<Result<Vec<_>, _>>();
+    let hole_cards = parse(&request.hole_cards)?;
+    let board = parse(&request.board)?;
+    let dead_cards = parse(&request.dead_cards)?;
+
+    if hole_cards.len() != Variant::Holdem.hole_cards() {
+        return Err("Exactly 2 hole cards are required".to_string());
+    }
+    if ![0, 3, 4, 5].contains(&board.len()) {
+        return Err("The board must have 0, 3, 4 or 5 cards".to_string());
+    }
+    if !(1..=7).contains(&request.opponents) {
+        return Err("Opponents must be between 1 and 7".to_string());
+    }
+    if !(1..=MAX_SIMULATIONS).contains(&request.sims) {
+        return Err(format!("Sims must be between 1 and {}", MAX_SIMULATIONS));
+    }
+
+    let known: Vec<Card> = hole_cards.iter().chain(&board).copied().collect();
+    check_no_duplicate_cards(known.iter().chain(&dead_cards))?;
+
+    let unseen: Vec<Card> = standard_deck().into_iter().filter(|c| !known.contains(c)).collect();
+    if let Some(preset) = request.opponent_range {
+        let ranges = vec![Range::preset(preset); request.opponents];
+        return Ok(simulate_win_probability_against_ranges(
+            &hole_cards,
+            &ranges,
+            &board,
+            &unseen,
+            &dead_cards,
+            request.sims,
+        ));
+    }
+    let equity = simulate_win_probability_hidden(
+        &hole_cards,
+        request.opponents,
+        &board,
+        &unseen,
+        &dead_cards,
+        request.sims,
+        Variant::Holdem,
+    );
+    Ok((equity, request.sims))
+}
+
+const MAX_BENCHMARK_HANDS: usize = 10_000;
+const BENCHMARK_SIMULATIONS: usize = 100;
+
+/// Times `hands` random heads-up deals, each evaluated at the river and simulated from the flop.
+/// Works on its own decks, so it never touches a running game.
+fn run_benchmark(hands: usize) -> Result<BenchmarkReport, String> {
+    if !(1..=MAX_BENCHMARK_HANDS).contains(&hands) {
+        return Err(format!("Hands must be between 1 and {}", MAX_BENCHMARK_HANDS));
+    }
+    let mut rng = rand::thread_rng();
+    let started = std::time::Instant::now();
+    for _ in 0..hands {
+        let mut deck = standard_deck();
+        deck.shuffle(&mut rng);
+        let hole_cards = deal_hole_cards(&mut deck, 2, Variant::Holdem)?;
+        let board = deck.split_off(deck.len() - 5);
+        for cards in &hole_cards {
+            let seven: Vec<Card> = cards.iter().chain(&board).copied().collect();
+            std::hint::black_box(evaluate_best_hand(&seven));
+        }
+        std::hint::black_box(simulate_win_probability(
+            &hole_cards[0],
+            &hole_cards[1..],
+            None,
+            &board[..3],
+            &deck,
+            &[],
+            BENCHMARK_SIMULATIONS,
+            Variant::Holdem,
+        ));
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+    Ok(BenchmarkReport { hands, elapsed_ms: elapsed * 1000.0, hands_per_second: hands as f64 / elapsed })
+}
+
+/// Deals the variant's hole cards to each of `players` hands from the top of the deck.
+/// Fails without touching the deck if it couldn't also cover the variant's full board.
+fn deal_hole_cards(deck: &mut Vec<Card>, players: usize, variant: Variant) -> Result<Vec<Vec<Card>>, String> {
+    let per_player = variant.hole_cards();
+    let needed = players * per_player + variant.board_cards();
+    if deck.len() < needed {
+        return Err(format!(
+            "Not enough cards: dealing {} players needs {} cards but the deck has {}",
+            players, needed, deck.len()
+        ));
+    }
+    Ok((0..players).map(|_| deck.split_off(deck.len() - per_player)).collect())
+}
+
+/// Clamps a robot's desired bet or raise total to what's legal: at least the minimum, at most
+/// the maximum. Returns `None` when the robot can't raise or the sizing came out at zero, in which
+/// case it should check or call instead.
+fn robot_bet_size(legal: &LegalActions, desired: u32) -> Option<u32> {
+    let can_raise = legal.actions.iter().any(|a| matches!(a, ActionType::Bet | ActionType::Raise));
+    if !can_raise || desired == 0 {
+        return None;
+    }
+    Some(desired.clamp(legal.min_raise, legal.max_raise))
+}
+
+/// Robots with fewer than this many big blinds play push/fold.
+const SHORT_STACK_BIG_BLINDS: u32 = 10;
+
+/// Seconds of time bank given back to each player at the start of every hand, up to the full bank.
+const TIME_BANK_REFILL_SECS: u32 = 5;
+
+/// A short stack's only two options.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PushFold {
+    Shove,
+    Fold,
+}
+
+/// The expected gain from shoving `stack` into `pot` rather than folding, assuming the shove is
+/// called: the pot and the caller's matching stack are won with probability `equity`, and the
+/// stack is lost otherwise. Folding gains nothing from here on.
+fn shove_ev(stack: u32, pot: u32, equity: f64) -> f64 {
+    equity * (f64::from(pot) + f64::from(stack)) - (1.0 - equity) * f64::from(stack)
+}
+
+/// Whichever of shoving and folding is worth more.
+fn push_fold(stack: u32, pot: u32, equity: f64) -> PushFold {
+    if shove_ev(stack, pot, equity) > 0.0 {
+        PushFold::Shove
+    } else {
+        PushFold::Fold
+    }
+}
+
+/// Picks the tell a robot shows while taking `action`, in keeping with its style and whether its
+/// hand is `strong`. A deceptive robot acts strong when weak and weak when strong. The same `seed`
+/// always picks the same tell.
+fn robot_tell(personality: &RobotPersonality, action: &ActionType, strong: bool, seed: u64) -> Option<String> {
+    let aggressive = matches!(action, ActionType::Bet | ActionType::Raise);
+    let tells: &[&str] = match (personality.style.as_str(), action) {
+        (_, ActionType::Show | ActionType::Muck) => return None,
+        ("Deceptive", _) if aggressive && !strong => &["smirks confidently", "stares you down", "stacks chips with a flourish"],
+        ("Deceptive", _) if aggressive => &["sighs and shrugs", "glances away, looking bored"],
+        ("Deceptive", ActionType::Fold) => &["mutters about bad luck"],
+        ("Deceptive", _) => &["taps the table casually", "hums a tune"],
+        ("Lucky", _) if aggressive && strong => &["kisses a lucky charm", "grins ear to ear"],
+        ("Lucky", _) if aggressive => &["rubs a rabbit's foot nervously"],
+        ("Lucky", ActionType::Fold) => &["shrugs it off"],
+        ("Lucky", _) => &["crosses fingers", "whistles"],
+        ("Calculating", _) if aggressive && strong => &["adjusts glasses", "recalculates silently"],
+        ("Calculating", _) if aggressive => &["pauses a beat too long"],
+        ("Calculating", ActionType::Fold) => &["logs the hand"],
+        ("Calculating", _) => &["nods once", "blinks steadily"],
+        ("Conservative", _) if aggressive && strong => &["sits up straight", "pushes chips in neatly"],
+        ("Conservative", _) if aggressive => &["hands tremble slightly"],
+        ("Conservative", ActionType::Fold) => &["folds without a second look"],
+        ("Conservative", _) => &["hesitates", "checks the cards again"],
+        _ if strong => &["leans forward"],
+        _ => &["leans back"],
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    tells.choose(&mut rng).map(|tell| tell.to_string())
+}
+
+/// The robots a new table is seated with, handed out round the seats in this order.
+fn robot_personalities() -> [RobotPersonality; 4] {
+    [
+        RobotPersonality {
+            name: "PokerBot 3000".to_string(),
+            emoji: "🤖".to_string(),
+            style: "Calculating".to_string(),
+            description: "A cold, calculating machine that plays by the numbers".to_string(),
+            aggression: 0.7,
+            bluff_frequency: 0.3,
+            patience: 0.8,
+            risk_tolerance: 0.6,
+        },
+        RobotPersonality {
+            name: "Lucky Larry".to_string(),
+            emoji: "🍀".to_string(),
+            style: "Lucky".to_string(),
+            description: "Always seems to get the cards he needs".to_string(),
+            aggression: 0.5,
+            bluff_frequency: 0.6,
+            patience: 0.4,
+            risk_tolerance: 0.8,
+        },
+        RobotPersonality {
+            name: "Bluff Master".to_string(),
+            emoji: "🎭".to_string(),
+            style: "Deceptive".to_string(),
+            description: "Loves to bluff and keep you guessing".to_string(),
+            aggression: 0.8,
+            bluff_frequency: 0.8,
+            patience: 0.3,
+            risk_tolerance: 0.9,
+        },
+        RobotPersonality {
+            name: "Safe Sally".to_string(),
+            emoji: "🛡️".to_string(),
+            style: "Conservative".to_string(),
+            description: "Plays it safe and waits for good hands".to_string(),
+            aggression: 0.3,
+            bluff_frequency: 0.2,
+            patience: 0.9,
+            risk_tolerance: 0.3,
+        },
+    ]
 }
 
 impl GameState {
     /// Creates a new game with the specified number of players (between 2 and 8).
-    fn new(num_players: usize, game_mode: GameMode, starting_chips: u32) -> Self {
-        if num_players < 2 || num_players > 8 {
-            panic!("Number of players must be between 2 and 8");
-        }
+    fn new(config: &NewGameQuery) -> Result<Self, String> {
+        let mut rng = config.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        Self::new_with_deck(config, shuffled_deck(config.variant, &mut rng))
+    }
 
-        // Generate a full 52-card deck.
-        let mut deck = Vec::with_capacity(52);
-        for &suit in &[Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades] {
-            for &rank in &[
-                Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
-                Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King, Rank::Ace,
-            ] {
-                deck.push(Card { suit, rank });
+    /// Creates a new game whose first hand is dealt from `deck` as given instead of a shuffled one,
+    /// first card first: hole cards seat by seat from seat 0, then the board. `deck` must be the
+    /// variant's full deck. Later hands are shuffled as usual.
+    fn new_with_deck(config: &NewGameQuery, deck: Vec<Card>) -> Result<Self, String> {
+        let num_players = config.num_players;
+        if !(2..=8).contains(&num_players) {
+            return Err(format!("Number of players must be between 2 and 8, got {}", num_players));
+        }
+        if config.starting_chips == 0 {
+            return Err("Starting chips must be greater than 0".to_string());
+        }
+        if !(0.0..=1.0).contains(&config.difficulty) {
+            return Err(format!("Difficulty must be between 0 and 1, got {}", config.difficulty));
+        }
+        if !(1..=num_players).contains(&config.human_count) {
+            return Err(format!("Human players must be between 1 and {}, got {}", num_players, config.human_count));
+        }
+        if config.allow_rebuy && config.rebuy_amount == 0 {
+            return Err("Rebuy amount must be greater than 0 when rebuys are allowed".to_string());
+        }
+        if config.straddles as usize > num_players - 2 {
+            return Err(format!(
+                "At most {} straddles fit at a {}-handed table, got {}",
+                num_players - 2, num_players, config.straddles
+            ));
+        }
+        if !(0.0..=1.0).contains(&config.rake_percent) {
+            return Err(format!("Rake must be between 0 and 1, got {}", config.rake_percent));
+        }
+        if let Some(stacks) = &config.stacks {
+            if stacks.len() != num_players {
+                return Err(format!("Expected {} stacks, one per player, got {}", num_players, stacks.len()));
+            }
+            if stacks.contains(&0) {
+                return Err("Every stack must be greater than 0".to_string());
+            }
+        }
+        if let Some(names) = &config.player_names {
+            if names.len() != num_players {
+                return Err(format!("Expected {} names, one per player, got {}", num_players, names.len()));
+            }
+            if names.iter().any(|name| name.trim().is_empty()) {
+                return Err("Player names must not be empty".to_string());
             }
         }
 
-        let mut rng = rand::thread_rng();
-        deck.shuffle(&mut rng);
+        let full_deck = config.variant.deck();
+        if deck.len() != full_deck.len() {
+            return Err(format!("A deck needs {} cards, got {}", full_deck.len(), deck.len()));
+        }
+        check_no_duplicate_cards(&deck)?;
+        if let Some(card) = deck.iter().find(|c| !full_deck.contains(c)) {
+            return Err(format!("{} isn't in this variant's deck", card));
+        }
+        // Cards are dealt from the end of the deck.
+        let mut deck: Vec<Card> = deck.into_iter().rev().collect();
+        let mut hands = deal_hole_cards(&mut deck, num_players, config.variant)?.into_iter();
 
-        let robot_personalities = vec![
-            RobotPersonality {
-                name: "PokerBot 3000".to_string(),
-                emoji: "🤖".to_string(),
-                style: "Calculating".to_string(),
-                description: "A cold, calculating machine that plays by the numbers".to_string(),
-                aggression: 0.7,
-                bluff_frequency: 0.3,
-                patience: 0.8,
-                risk_tolerance: 0.6,
-            },
-            RobotPersonality {
-                name: "Lucky Larry".to_string(),
-                emoji: "🍀".to_string(),
-                style: "Lucky".to_string(),
-                description: "Always seems to get the cards he needs".to_string(),
-                aggression: 0.5,
-                bluff_frequency: 0.6,
-                patience: 0.4,
-                risk_tolerance: 0.8,
-            },
-            RobotPersonality {
-                name: "Bluff Master".to_string(),
-                emoji: "🎭".to_string(),
-                style: "Deceptive".to_string(),
-                description: "Loves to bluff and keep you guessing".to_string(),
-                aggression: 0.8,
-                bluff_frequency: 0.8,
-                patience: 0.3,
-                risk_tolerance: 0.9,
-            },
-            RobotPersonality {
-                name: "Safe Sally".to_string(),
-                emoji: "🛡️".to_string(),
-                style: "Conservative".to_string(),
-                description: "Plays it safe and waits for good hands".to_string(),
-                aggression: 0.3,
-                bluff_frequency: 0.2,
-                patience: 0.9,
-                risk_tolerance: 0.3,
-            },
-        ];
+        let personalities = robot_personalities();
 
         let mut players = Vec::with_capacity(num_players);
         for i in 0..num_players {
-            let card1 = deck.pop().expect("Deck should have enough cards");
-            let card2 = deck.pop().expect("Deck should have enough cards");
-            let is_robot = i > 0;
+            let cards = hands.next().unwrap_or_default();
+            let is_robot = i >= config.human_count;
             
             let personality = if is_robot {
-                Some(robot_personalities[i % robot_personalities.len()].clone())
+                Some(personalities[i % personalities.len()].clone())
             } else {
                 None
             };
 
             players.push(Player {
-                cards: vec![card1, card2],
+                cards,
                 win_probability: 0.0,
-                chips: starting_chips,
+                chips: config.stacks.as_ref().map_or(config.starting_chips, |stacks| stacks[i]),
                 is_robot,
-                name: if is_robot {
+                name: if let Some(names) = &config.player_names {
+                    names[i].trim().to_string()
+                } else if is_robot {
                     format!("{} {}", personality.as_ref().unwrap().emoji, personality.as_ref().unwrap().name)
-                } else {
+                } else if config.human_count == 1 {
                     "You".to_string()
+                } else {
+                    format!("Player {}", i + 1)
                 },
                 current_bet: 0,
+                total_bet: 0,
                 personality,
+                equity_history: Vec::new(),
+                time_bank_secs: config.time_bank_secs,
             });
         }
 
@@ -441,9 +2175,14 @@ impl GameState {
             community_cards: Vec::new(),
             pot: 0,
             current_bet: 0,
-            game_mode,
+            game_mode: config.game_mode.clone(),
             current_player: 0,
+            variant: config.variant,
+            dealer: 0,
+            small_blind: config.small_blind,
+            big_blind: config.big_blind,
             last_action: None,
+            equity_mode: config.equity_mode,
             stats: GameStats {
                 start_time: chrono::Utc::now(),
                 end_time: None,
@@ -454,24 +2193,61 @@ impl GameState {
                     biggest_pot: 0,
                     best_hand: String::new(),
                     favorite_action: String::new(),
+                    rebuys: 0,
                 }; num_players],
                 total_hands: 0,
+                total_pot: 0,
                 average_pot: 0,
                 biggest_pot: 0,
+                total_rake: 0,
             },
             hand_history: Vec::new(),
+            winner: None,
+            action_timeout_secs: config.action_timeout_secs,
+            action_deadline: None,
+            difficulty: config.difficulty,
+            last_raise: 0,
+            ante: config.ante,
+            straddle: config.straddle,
+            straddles: config.straddles,
+            last_straddler: None,
+            show_probabilities: config.show_probabilities,
+            betting_structure: config.betting_structure,
+            robot_delay_ms: config.robot_delay_ms,
+            allow_rebuy: config.allow_rebuy,
+            rebuy_amount: config.rebuy_amount,
+            time_bank_secs: config.time_bank_secs,
+            blind_seats: None,
+            rake_percent: config.rake_percent,
+            rake_cap: config.rake_cap,
+            drawn: Vec::new(),
+            discards: Vec::new(),
+            seed: config.seed,
+            version: 0,
+            snapshots: VecDeque::new(),
         };
-        game.update_probabilities();
-        game
+        game.validate_no_duplicates()?;
+        game.start_new_hand();
+        game.open_hand().map_err(|e| e.to_string())?;
+        Ok(game)
     }
 
     /// Updates win probabilities for all players based on the current state.
     fn update_probabilities(&mut self) {
-        const NUM_SIMULATIONS: usize = 1000;
+        self.update_probabilities_with(DEFAULT_EQUITY_SIMULATIONS);
+    }
+
+    /// Updates win probabilities like `update_probabilities`, sampling `num_simulations` boards
+    /// when they aren't enumerated. The samples come from `game_rng`, so a seeded game's robots
+    /// see the same equities, and give the same tells, every time. Recomputing on the same street
+    /// replaces that street's entry in the equity history.
+    fn update_probabilities_with(&mut self, num_simulations: usize) {
         // Use the current deck as the remaining deck.
         let remaining_deck = self.deck.clone();
+        let mut rng = self.game_rng();
 
-        // First collect all opponent cards for each player
+        // First collect all opponent cards for each player. Folded players have no cards and
+        // aren't opponents any more.
         let opponent_cards: Vec<Vec<Vec<Card>>> = self.players
             .iter()
             .enumerate()
@@ -479,223 +2255,1051 @@ impl GameState {
                 self.players
                     .iter()
                     .enumerate()
-                    .filter(|&(j, _)| j != i)
+                    .filter(|&(j, p)| j != i && !p.cards.is_empty())
                     .map(|(_, p)| p.cards.clone())
                     .collect()
             })
             .collect();
 
+        let board_needed = self.variant.board_cards().saturating_sub(self.community_cards.len());
+        let enumerate_boards = n_choose_k(remaining_deck.len(), board_needed) <= MAX_ENUMERATED_BOARDS;
+
         // Then update probabilities
         for (i, player) in self.players.iter_mut().enumerate() {
-            if player.cards.len() != 2 {
+            if player.cards.len() != self.variant.hole_cards() {
                 player.win_probability = 0.0;
                 continue;
             }
 
-            let prob = simulate_win_probability(
-                &player.cards,
-                &opponent_cards[i],
-                &self.community_cards,
-                &remaining_deck,
-                NUM_SIMULATIONS,
-            );
+            let prob = match self.equity_mode {
+                EquityMode::Exact if enumerate_boards => exact_equity(
+                    &player.cards,
+                    &opponent_cards[i],
+                    &self.community_cards,
+                    &remaining_deck,
+                    self.variant,
+                ),
+                EquityMode::Exact => simulate_win_probability_with(
+                    &player.cards,
+                    &opponent_cards[i],
+                    None,
+                    &self.community_cards,
+                    &remaining_deck,
+                    &[],
+                    num_simulations,
+                    self.variant,
+                    &mut rng,
+                ),
+                EquityMode::Hidden => {
+                    // From this player's seat, the opponents' cards are as unknown as the deck.
+                    let mut unseen = remaining_deck.clone();
+                    unseen.extend(opponent_cards[i].iter().flatten().cloned());
+                    simulate_win_probability_hidden_with(
+                        &player.cards,
+                        opponent_cards[i].len(),
+                        &self.community_cards,
+                        &unseen,
+                        &[],
+                        num_simulations,
+                        self.variant,
+                        &mut rng,
+                    )
+                }
+            };
             player.win_probability = prob;
         }
+
+        let phase = self.street();
+        for player in self.players.iter_mut().filter(|p| !p.cards.is_empty()) {
+            if player.equity_history.last().is_some_and(|(p, _)| *p == phase) {
+                player.equity_history.pop();
+            }
+            player.equity_history.push((phase.clone(), player.win_probability));
+        }
+    }
+
+    /// Each contender's chances over the rest of the board once the betting is over with a
+    /// player all-in, as shown on TV. The runouts are enumerated when there are few enough of
+    /// them and sampled otherwise.
+    fn all_in_equity(&self) -> Result<Vec<SeatEquity>, GameError> {
+        let contenders: Vec<usize> = (0..self.players.len()).filter(|&i| !self.players[i].cards.is_empty()).collect();
+        let betting_over = contenders.iter().filter(|&&i| self.players[i].can_act()).count() <= 1;
+        if contenders.len() < 2
+            || !contenders.iter().any(|&i| self.players[i].is_all_in())
+            || !betting_over
+            || !self.betting_round_complete()
+            || self.community_cards.len() >= self.variant.board_cards()
+        {
+            return Err("Nobody is all-in with cards still to come".into());
+        }
+
+        Ok(self.equity_on_board(&contenders, &self.community_cards, &self.deck))
+    }
+
+    /// Each contender's chances with the rest of `board` dealt from `deck`, enumerating the
+    /// runouts when there are few enough of them and sampling otherwise.
+    fn equity_on_board(&self, contenders: &[usize], board: &[Card], deck: &[Card]) -> Vec<SeatEquity> {
+        let board_needed = self.variant.board_cards().saturating_sub(board.len());
+        let enumerate_boards = n_choose_k(deck.len(), board_needed) <= MAX_ENUMERATED_BOARDS;
+        contenders
+            .iter()
+            .map(|&seat| {
+                let opponents: Vec<Vec<Card>> =
+                    contenders.iter().filter(|&&i| i != seat).map(|&i| self.players[i].cards.clone()).collect();
+                let hole = &self.players[seat].cards;
+                let equity = if enumerate_boards {
+                    exact_equity_breakdown(hole, &opponents, board, deck, self.variant)
+                } else {
+                    simulate_equity_breakdown(hole, &opponents, board, deck, &[], DEFAULT_EQUITY_SIMULATIONS, self.variant)
+                };
+                SeatEquity { seat, equity }
+            })
+            .collect()
+    }
+
+    /// Each player still in the hand's exact chances had the board been `board` instead, e.g.
+    /// with a different turn card. The rest of a partial board is enumerated from every card not
+    /// in a player's hand, the discards or `board`, so only a flop, turn or river is accepted.
+    /// Against robots only the humans' and the hands shown at showdown are returned, since the
+    /// others would give the robots' cards away. The live game is left as it is.
+    fn what_if(&self, board: &[Card]) -> Result<Vec<SeatEquity>, GameError> {
+        if self.variant.board_cards() == 0 {
+            return Err("Five-card draw has no board".into());
+        }
+        if ![3, 4, 5].contains(&board.len()) {
+            return Err("The board must have 3, 4 or 5 cards".into());
+        }
+        let contenders: Vec<usize> = (0..self.players.len()).filter(|&i| !self.players[i].cards.is_empty()).collect();
+        if contenders.is_empty() {
+            return Err("Nobody holds any cards".into());
+        }
+        let known: Vec<Card> = self.players.iter().flat_map(|p| p.cards.iter()).chain(&self.discards).copied().collect();
+        check_no_duplicate_cards(known.iter().chain(board))?;
+
+        let deck: Vec<Card> = self.variant.deck().into_iter().filter(|c| !known.contains(c) && !board.contains(c)).collect();
+        let visible = match self.game_mode {
+            GameMode::RobotPlay => self.with_hole_cards_hidden(|seat| self.players[seat].is_robot),
+            GameMode::Simulation => self.clone(),
+        };
+        Ok(contenders
+            .iter()
+            .filter(|&&seat| !visible.players[seat].cards.is_empty())
+            .map(|&seat| {
+                let opponents: Vec<Vec<Card>> =
+                    contenders.iter().filter(|&&i| i != seat).map(|&i| self.players[i].cards.clone()).collect();
+                let equity = exact_equity_breakdown(&self.players[seat].cards, &opponents, board, &deck, self.variant);
+                SeatEquity { seat, equity }
+            })
+            .collect())
+    }
+
+    /// Checks that no card appears twice across the hole cards, the board, the discards and the deck.
+    fn validate_no_duplicates(&self) -> Result<(), String> {
+        check_no_duplicate_cards(self.cards_in_play())
+    }
+
+    /// Checks that the hole cards, the board, the discards and the deck together make up exactly
+    /// the variant's deck: nothing duplicated, missing or foreign.
+    fn check_deck_integrity(&self) -> Result<(), String> {
+        self.validate_no_duplicates()?;
+        let full_deck = self.variant.deck();
+        if let Some(card) = self.cards_in_play().find(|c| !full_deck.contains(c)) {
+            return Err(format!("{} isn't in this variant's deck", card));
+        }
+        let in_play: Vec<&Card> = self.cards_in_play().collect();
+        let missing: Vec<String> = full_deck.iter().filter(|c| !in_play.contains(c)).map(|c| c.to_string()).collect();
+        if !missing.is_empty() {
+            return Err(format!("Missing cards: {}", missing.join(" ")));
+        }
+        Ok(())
+    }
+
+    /// Every card the table accounts for this hand.
+    fn cards_in_play(&self) -> impl Iterator<Item = &Card> {
+        let hole_cards = self.players.iter().flat_map(|p| &p.cards);
+        hole_cards.chain(&self.community_cards).chain(&self.discards).chain(&self.deck)
+    }
+
+    /// Every chip at the table: the players' stacks plus the pot. Chips only ever move between
+    /// the two, so this stays constant for the whole game.
+    fn total_chips(&self) -> u64 {
+        self.players.iter().map(|p| u64::from(p.chips)).sum::<u64>() + u64::from(self.pot)
+    }
+
+    /// Checks that `context` neither created nor destroyed chips. Debug builds panic on a leak;
+    /// release builds log it and carry on.
+    fn check_chip_conservation(&self, expected: u64, context: &str) {
+        let actual = self.total_chips();
+        debug_assert_eq!(actual, expected, "chip total changed during {}", context);
+        if actual != expected {
+            eprintln!("Warning: chip total drifted from {} to {} during {}", expected, actual, context);
+        }
+    }
+
+    /// Clears the round's bets so the next street's betting starts from zero.
+    fn reset_round_bets(&mut self) {
+        self.current_bet = 0;
+        self.last_raise = 0;
+        for player in &mut self.players {
+            player.current_bet = 0;
+        }
+    }
+
+    /// Who opens the betting after the preflop round: the first player left of the dealer who
+    /// still has a decision to make.
+    fn first_to_act_postflop(&self) -> usize {
+        self.next_seat_to_act(self.dealer)
+    }
+
+    /// Whether `cards` more community cards fit on the variant's board.
+    fn board_has_room(&self, cards: usize) -> bool {
+        self.community_cards.len() + cards <= self.variant.board_cards()
+    }
+
+    /// The betting round being played: the street for the size of the board, or in five-card
+    /// draw the round after the draw once everyone still in has drawn.
+    fn street(&self) -> GamePhase {
+        if self.variant == Variant::FiveCardDraw && self.draw_complete() {
+            GamePhase::AfterDraw
+        } else {
+            GamePhase::for_board(self.community_cards.len())
+        }
+    }
+
+    /// Whether every player still in a five-card draw hand has taken their draw.
+    fn draw_complete(&self) -> bool {
+        self.players.iter().enumerate().all(|(seat, p)| p.cards.is_empty() || self.drawn.contains(&seat))
     }
 
-    /// Deals the flop (3 community cards) and updates probabilities.
+    /// Deals the flop (3 community cards) and updates probabilities. Does nothing if the
+    /// board has no room for it, as in five-card draw.
     fn deal_flop(&mut self) {
+        if !self.board_has_room(3) {
+            return;
+        }
+        let chips_before = self.total_chips();
+        self.reset_round_bets();
         for _ in 0..3 {
             if let Some(card) = self.deck.pop() {
                 self.community_cards.push(card);
             }
         }
+        self.record_street();
+        self.current_player = self.first_to_act_postflop();
         self.update_probabilities();
+        self.check_chip_conservation(chips_before, "a street transition");
     }
 
-    /// Deals the turn (1 community card) and updates probabilities.
+    /// Deals the turn (1 community card) and updates probabilities. Does nothing if the
+    /// board has no room for it, as in five-card draw.
     fn deal_turn(&mut self) {
+        if !self.board_has_room(1) {
+            return;
+        }
+        let chips_before = self.total_chips();
+        self.reset_round_bets();
         if let Some(card) = self.deck.pop() {
             self.community_cards.push(card);
         }
+        self.record_street();
+        self.current_player = self.first_to_act_postflop();
         self.update_probabilities();
+        self.check_chip_conservation(chips_before, "a street transition");
     }
 
-    /// Deals the river (1 community card) and updates probabilities.
+    /// Deals the river (1 community card) and updates probabilities. Does nothing if the
+    /// board has no room for it, as in five-card draw.
     fn deal_river(&mut self) {
+        if !self.board_has_room(1) {
+            return;
+        }
+        let chips_before = self.total_chips();
+        self.reset_round_bets();
         if let Some(card) = self.deck.pop() {
             self.community_cards.push(card);
         }
+        self.record_street();
+        self.current_player = self.first_to_act_postflop();
+        self.update_probabilities();
+        self.check_chip_conservation(chips_before, "a street transition");
+    }
+
+    /// Deals whichever street comes next from the size of the board. The hand must still be
+    /// contested and short of the river, and the betting on the current street must be over.
+    fn deal_next_street(&mut self) -> Result<GamePhase, GameError> {
+        if self.at_showdown() {
+            return Err(GameError::HandOver);
+        }
+        let contested = self.players.iter().filter(|p| !p.cards.is_empty()).count() >= 2;
+        if !contested || self.community_cards.len() >= self.variant.board_cards() {
+            return Err(GameError::NoStreetToDeal);
+        }
+        if !self.betting_round_complete() {
+            return Err(GameError::BettingNotComplete);
+        }
+        match self.community_cards.len() {
+            0 => self.deal_flop(),
+            3 => self.deal_turn(),
+            4 => self.deal_river(),
+            _ => return Err(GameError::NoStreetToDeal),
+        }
+        Ok(GamePhase::for_board(self.community_cards.len()))
+    }
+
+    /// Swaps the cards at `discard` in a five-card draw hand for fresh ones from the deck, once the
+    /// betting is over. Each player draws once per hand; standing pat is an empty `discard`. Once
+    /// the last player has drawn, a second betting round opens from the seat left of the button.
+    /// Returns the replacement cards.
+    fn draw(&mut self, seat: usize, discard: &[usize]) -> Result<Vec<Card>, GameError> {
+        if self.variant != Variant::FiveCardDraw {
+            return Err("Drawing is only part of five-card draw".into());
+        }
+        if self.at_showdown() {
+            return Err(GameError::HandOver);
+        }
+        let player = self.players.get(seat).ok_or(GameError::NoSuchSeat)?;
+        if player.cards.is_empty() {
+            return Err("Only players still in the hand can draw".into());
+        }
+        if !self.betting_round_complete() {
+            return Err("The betting must finish before the draw".into());
+        }
+        if self.drawn.contains(&seat) {
+            return Err("Each player draws only once".into());
+        }
+        let mut unique = discard.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+        if unique.len() != discard.len() || discard.iter().any(|&i| i >= player.cards.len()) {
+            return Err(format!("Discards must be distinct card positions from 0 to {}", player.cards.len() - 1).into());
+        }
+        if self.deck.len() < discard.len() {
+            return Err("Not enough cards left in the deck to draw".into());
+        }
+
+        let mut replacements = Vec::with_capacity(discard.len());
+        for &i in discard {
+            let card = self.deck.pop().expect("the deck was checked to hold enough cards");
+            let old = std::mem::replace(&mut self.players[seat].cards[i], card);
+            self.discards.push(old);
+            replacements.push(card);
+        }
+        self.drawn.push(seat);
+        if self.draw_complete() {
+            self.reset_round_bets();
+            self.record_street();
+            self.current_player = self.first_to_act_postflop();
+        }
+        self.update_probabilities();
+        Ok(replacements)
+    }
+
+    /// The next seat after `seat` that was dealt into the current hand.
+    fn next_seat_in_hand(&self, seat: usize) -> usize {
+        let n = self.players.len();
+        (1..=n)
+            .map(|offset| (seat + offset) % n)
+            .find(|&i| !self.players[i].cards.is_empty())
+            .unwrap_or(seat)
+    }
+
+    /// The next seat after `seat` whose player can still act, skipping folded and all-in
+    /// players. Returns `seat` itself when nobody else can.
+    fn next_seat_to_act(&self, seat: usize) -> usize {
+        let n = self.players.len();
+        (1..n)
+            .map(|offset| (seat + offset) % n)
+            .find(|&i| self.players[i].can_act())
+            .unwrap_or(seat)
+    }
+
+    /// How many straddles each hand starts with: `straddles`, or one when only `straddle` is set.
+    fn straddle_count(&self) -> u32 {
+        if self.straddle {
+            self.straddles.max(1)
+        } else {
+            self.straddles
+        }
+    }
+
+    /// Moves chips from a player's stack into the pot. Every counter is updated with checked
+    /// arithmetic so an oversized amount is rejected before anything changes.
+    fn commit_chips(&mut self, seat: usize, amount: u32) -> Result<(), GameError> {
+        let player = &self.players[seat];
+        let chips = player.chips.checked_sub(amount).ok_or(GameError::NotEnoughChips)?;
+        let pot = self.pot.checked_add(amount).ok_or(GameError::PotOverflow)?;
+        let current_bet = player.current_bet.checked_add(amount).ok_or(GameError::PotOverflow)?;
+        let total_bet = player.total_bet.checked_add(amount).ok_or(GameError::PotOverflow)?;
+
+        let player = &mut self.players[seat];
+        player.chips = chips;
+        player.current_bet = current_bet;
+        player.total_bet = total_bet;
+        self.pot = pot;
+        Ok(())
+    }
+
+    /// Moves a forced bet (blind) from a player into the pot, capped at their stack.
+    fn post_blind(&mut self, seat: usize, amount: u32, kind: BlindKind) -> Result<(), GameError> {
+        let posted = amount.min(self.players[seat].chips);
+        self.commit_chips(seat, posted)?;
+        self.current_bet = self.current_bet.max(self.players[seat].current_bet);
+        self.record_post(seat, kind, posted);
+        Ok(())
+    }
+
+    /// Moves an ante into the pot, capped at the player's stack. Antes are dead money, so they
+    /// don't count toward the player's bet for the round.
+    fn post_ante(&mut self, seat: usize, amount: u32) -> Result<(), GameError> {
+        let posted = amount.min(self.players[seat].chips);
+        self.commit_chips(seat, posted)?;
+        self.players[seat].current_bet -= posted;
+        self.record_post(seat, BlindKind::Ante, posted);
+        Ok(())
+    }
+
+    /// Notes a forced bet in the current hand's history.
+    fn record_post(&mut self, seat: usize, kind: BlindKind, amount: u32) {
+        if let Some(current_hand) = self.hand_history.last_mut() {
+            current_hand.posts.push(PostedBlind { seat, kind, amount });
+        }
+    }
+
+    /// Returns the last player with chips once every other player is busted and no pot is
+    /// outstanding.
+    fn is_game_over(&self) -> Option<usize> {
+        if self.pot > 0 {
+            return None;
+        }
+        let mut with_chips = self.players.iter().enumerate().filter(|(_, p)| p.chips > 0);
+        match (with_chips.next(), with_chips.next()) {
+            (Some((i, _)), None) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Records the end of the match if it is over, returning the winner.
+    fn check_game_over(&mut self) -> Option<usize> {
+        let winner = self.is_game_over()?;
+        if self.winner.is_none() {
+            self.winner = Some(winner);
+            self.stats.end_time = Some(chrono::Utc::now());
+        }
+        Some(winner)
+    }
+
+    /// Tops a busted player back up to the rebuy amount between hands, so they're dealt into the
+    /// next one. A game that ended when they busted carries on.
+    fn rebuy(&mut self, seat: usize) -> Result<(), GameError> {
+        if !self.allow_rebuy {
+            return Err(GameError::RebuyNotAllowed);
+        }
+        let player = self.players.get_mut(seat).ok_or(GameError::NoSuchSeat)?;
+        if player.chips > 0 {
+            return Err(GameError::NotBusted);
+        }
+        if self.pot > 0 {
+            return Err(GameError::HandInProgress);
+        }
+        player.chips = self.rebuy_amount;
+        self.stats.players[seat].rebuys += 1;
+        self.winner = None;
+        self.stats.end_time = None;
+        Ok(())
+    }
+
+    /// Starts the next hand: reshuffles a full deck, clears the board and bets, moves the dealer
+    /// button, deals two hole cards to every player who still has chips and posts the blinds.
+    /// Chip stacks and session statistics carry over.
+    fn deal_new_hand(&mut self) -> Result<(), GameError> {
+        if self.pot > 0 {
+            return Err(GameError::HandInProgress);
+        }
+        if let Some(winner) = self.check_game_over() {
+            return Err(GameError::GameOver { winner: self.players[winner].name.clone() });
+        }
+
+        let chips_before = self.total_chips();
+        // Deal from a fresh deck first so a failure leaves the table untouched.
+        let mut deck = shuffled_deck(self.variant, &mut self.game_rng());
+        let receiving = self.players.iter().filter(|p| p.chips > 0).count();
+        let mut hands = deal_hole_cards(&mut deck, receiving, self.variant)?.into_iter();
+
+        self.deck = deck;
+        self.community_cards.clear();
+        self.drawn.clear();
+        self.discards.clear();
+        self.current_bet = 0;
+        self.last_raise = 0;
+        self.last_action = None;
+        for player in &mut self.players {
+            player.cards.clear();
+            player.current_bet = 0;
+            player.total_bet = 0;
+            player.win_probability = 0.0;
+            player.equity_history.clear();
+            player.time_bank_secs = (player.time_bank_secs + TIME_BANK_REFILL_SECS).min(self.time_bank_secs);
+        }
+
+        // Busted players are skipped entirely.
+        for player in self.players.iter_mut().filter(|p| p.chips > 0) {
+            player.cards = hands.next().unwrap_or_default();
+        }
+        self.dealer = self.next_seat_in_hand(self.dealer);
+        self.start_new_hand();
+        self.open_hand()?;
+        self.check_chip_conservation(chips_before, "a new hand");
+        Ok(())
+    }
+
+    /// Opens a freshly dealt hand, the first of the game included: posts the antes, blinds and
+    /// straddles around the button and hands the action to the first player preflop.
+    fn open_hand(&mut self) -> Result<(), GameError> {
+        if self.ante > 0 {
+            for seat in 0..self.players.len() {
+                if !self.players[seat].cards.is_empty() {
+                    self.post_ante(seat, self.ante)?;
+                }
+            }
+        }
+
+        // Heads-up, the dealer posts the small blind and acts first preflop.
+        let dealt = self.players.iter().filter(|p| !p.cards.is_empty()).count();
+        let small_blind_seat = if dealt == 2 { self.dealer } else { self.next_seat_in_hand(self.dealer) };
+        let big_blind_seat = self.next_seat_in_hand(small_blind_seat);
+        self.post_blind(small_blind_seat, self.small_blind, BlindKind::SmallBlind)?;
+        self.post_blind(big_blind_seat, self.big_blind, BlindKind::BigBlind)?;
+        self.blind_seats = Some((small_blind_seat, big_blind_seat));
+        let mut last_blind_seat = big_blind_seat;
+        // Straddles go round from the seat after the big blind, each doubling the one before and
+        // stopping short of the blinds, so none are posted heads-up. The last straddler acts
+        // last preflop, and a raise must at least double the live bet. A straddler too short to
+        // post in full is all-in for what they have.
+        self.last_straddler = None;
+        let straddles = self.straddle_count().min(dealt.saturating_sub(2) as u32);
+        let mut straddle = self.big_blind;
+        for _ in 0..straddles {
+            let straddle_seat = self.next_seat_in_hand(last_blind_seat);
+            straddle = straddle.saturating_mul(2);
+            self.post_blind(straddle_seat, straddle, BlindKind::Straddle)?;
+            self.last_raise = self.current_bet;
+            self.last_straddler = Some(straddle_seat);
+            last_blind_seat = straddle_seat;
+        }
+        self.current_player = self.next_seat_to_act(last_blind_seat);
+
+        self.record_street();
         self.update_probabilities();
+
+        self.advance_until_human()?;
+        self.refresh_action_deadline();
+        Ok(())
+    }
+
+    /// Works out what the player in `seat` may legally do. `handle_action` validates every
+    /// action against this, so the advertised options and the enforced rules stay in step.
+    fn legal_actions(&self, seat: usize) -> Result<LegalActions, GameError> {
+        let player = self.players.get(seat).ok_or(GameError::NoSuchSeat)?;
+        // Once the pot is awarded, the only choice left is whether to show an unrevealed hand.
+        if let Some(hand) = self.hand_history.last().filter(|_| self.at_showdown()) {
+            let actions = if !player.cards.is_empty() && !hand.revealed.contains(&seat) {
+                vec![ActionType::Show, ActionType::Muck]
+            } else {
+                Vec::new()
+            };
+            return Ok(LegalActions { player_index: seat, actions, call_amount: 0, min_raise: 0, max_raise: 0 });
+        }
+        let owed = self.current_bet.saturating_sub(player.current_bet);
+        // A player who can't cover the full call goes all-in for what they have.
+        let call_amount = owed.min(player.chips);
+        // Raise amounts are round totals; the most a player can raise to is their whole stack.
+        let all_in = player.current_bet.saturating_add(player.chips);
+        // A bet or raise must add at least the big blind, and at least as much as the largest
+        // bet or raise before it this round. A stack too short for that may still go all-in.
+        let increment = match self.betting_structure {
+            BettingStructure::FixedLimit => self.fixed_limit_bet(),
+            _ => self.last_raise.max(self.big_blind).max(1),
+        };
+        let min_total = self.current_bet.saturating_add(increment);
+        let limit = match self.betting_structure {
+            BettingStructure::NoLimit => all_in,
+            // The pot counts the player's call as already made; a minimum raise is always allowed.
+            BettingStructure::PotLimit => self.current_bet.saturating_add(self.pot).saturating_add(owed).max(min_total),
+            BettingStructure::FixedLimit => min_total,
+        };
+        let max_raise = all_in.min(limit);
+        let min_raise = min_total.min(max_raise);
+
+        let mut actions = vec![ActionType::Fold];
+        if owed == 0 {
+            actions.push(ActionType::Check);
+        } else {
+            actions.push(ActionType::Call);
+        }
+        if max_raise > self.current_bet {
+            if self.current_bet == 0 {
+                actions.push(ActionType::Bet);
+            } else {
+                actions.push(ActionType::Raise);
+            }
+        }
+
+        Ok(LegalActions { player_index: seat, actions, call_amount, min_raise, max_raise })
+    }
+
+    /// The fixed bet size for the current street under `FixedLimit`.
+    fn fixed_limit_bet(&self) -> u32 {
+        let big_blind = self.big_blind.max(1);
+        if self.community_cards.len() >= 4 {
+            big_blind.saturating_mul(2)
+        } else {
+            big_blind
+        }
     }
 
-    fn handle_action(&mut self, action: Action) -> Result<(), String> {
-        let player = &mut self.players[action.player_index];
-        
+    /// Applies a single player's action: validates it against `legal_actions`, moves the chips
+    /// and passes the turn on. Robots waiting after it are left to `advance_until_human`.
+    fn handle_action(&mut self, action: Action) -> Result<(), GameError> {
+        let chips_before = self.total_chips();
+        let seat = action.player_index;
+        let legal = self.legal_actions(seat)?;
+        let allowed = legal.actions.contains(&action.action_type);
+
+        let showdown_action = matches!(action.action_type, ActionType::Show | ActionType::Muck);
+        if showdown_action != self.at_showdown() {
+            return Err(if showdown_action { GameError::NotAtShowdown } else { GameError::HandOver });
+        }
+        // Acting out of turn would throw the rotation off, as would acting from a seat with no
+        // decision left. Showing or mucking happens once the betting is over, so it doesn't wait.
+        if !showdown_action && (seat != self.current_player || !self.players[seat].can_act()) {
+            let waiting_on = self.players[self.current_player].name.clone();
+            return Err(GameError::OutOfTurn { waiting_on });
+        }
+
         match action.action_type {
+            ActionType::Show | ActionType::Muck => {
+                if !allowed {
+                    return Err(GameError::AlreadyShown);
+                }
+                if action.action_type == ActionType::Show {
+                    if let Some(hand) = self.hand_history.last_mut() {
+                        hand.revealed.push(seat);
+                    }
+                }
+                // The pot is settled, so there's no turn to pass on.
+                self.last_action = Some(action.clone());
+                self.record_action(&action);
+                return Ok(());
+            },
             ActionType::Fold => {
-                player.cards.clear();
+                let folded = std::mem::take(&mut self.players[seat].cards);
+                self.discards.extend(folded);
             },
             ActionType::Check => {
-                if self.current_bet > 0 {
-                    return Err("Cannot check when there's a bet".to_string());
+                // Checking is only possible when the player has already matched the bet,
+                // e.g. the big blind taking their option.
+                if !allowed {
+                    return Err(GameError::IllegalCheck);
                 }
             },
             ActionType::Call => {
-                let call_amount = self.current_bet.saturating_sub(player.current_bet);
-                if player.chips < call_amount {
-                    return Err("Not enough chips to call".to_string());
+                if !allowed {
+                    return Err(GameError::NothingToCall);
+                }
+                self.commit_chips(seat, legal.call_amount)?;
+            },
+            ActionType::Bet | ActionType::Raise => {
+                let verb = if action.action_type == ActionType::Bet { "Bet" } else { "Raise" };
+                // The amount is what the player's bet for this round is raised to.
+                let amount = action.amount.ok_or(GameError::AmountRequired { verb })?;
+                let all_in = self.players[seat].current_bet.saturating_add(self.players[seat].chips);
+                // A stack that can't get past the bet has no raise to make, only an all-in call.
+                if self.current_bet > 0 && all_in <= self.current_bet {
+                    return Err(GameError::AllInCallOnly { call: legal.call_amount });
                 }
-                player.chips -= call_amount;
-                self.pot += call_amount;
-                player.current_bet += call_amount;
-            },
-            ActionType::Bet => {
-                let amount = action.amount.ok_or("Bet amount required")?;
-                if amount <= self.current_bet {
-                    return Err("Bet must be higher than current bet".to_string());
+                // A stack that can't cover more than the minimum may only go all-in.
+                if legal.min_raise == all_in && amount != all_in {
+                    return Err(GameError::AllInOnly { verb, amount, all_in });
                 }
-                if player.chips < amount {
-                    return Err("Not enough chips to bet".to_string());
+                if amount < legal.min_raise {
+                    return Err(GameError::RaiseTooSmall { verb, amount, min: legal.min_raise });
                 }
-                player.chips -= amount;
-                self.pot += amount;
-                self.current_bet = amount;
-                player.current_bet = amount;
-            },
-            ActionType::Raise => {
-                let amount = action.amount.ok_or("Raise amount required")?;
-                if amount <= self.current_bet {
-                    return Err("Raise must be higher than current bet".to_string());
+                // Anything up to the whole stack (all-in) is allowed unless the betting
+                // structure caps it lower; beyond that it is rejected.
+                if amount > all_in {
+                    return Err(GameError::RaiseTooLarge { verb, amount, all_in });
+                }
+                if amount > legal.max_raise {
+                    return Err(GameError::OverLimit { verb, amount, max: legal.max_raise });
                 }
-                if player.chips < amount {
-                    return Err("Not enough chips to raise".to_string());
+                if !allowed {
+                    return Err(if action.action_type == ActionType::Bet {
+                        GameError::BetFacingBet
+                    } else {
+                        GameError::RaiseWithoutBet
+                    });
                 }
-                player.chips -= amount;
-                self.pot += amount;
+                let additional = amount - self.players[seat].current_bet;
+                self.commit_chips(seat, additional)?;
+                self.last_raise = self.last_raise.max(amount - self.current_bet);
                 self.current_bet = amount;
-                player.current_bet = amount;
             },
         }
 
         self.last_action = Some(action.clone());
-        
-        // Move to next player
-        self.current_player = (self.current_player + 1) % self.players.len();
-        
-        // If it's a robot's turn, make them act
-        if self.players[self.current_player].is_robot {
-            self.handle_robot_action()?;
+        // Snapshot the pot and board only after the action has been applied.
+        self.record_action(&action);
+
+        // Once everyone else has folded, the last player standing takes the pot unopposed.
+        let mut in_hand = (0..self.players.len()).filter(|&i| !self.players[i].cards.is_empty());
+        if let (Some(winner), None) = (in_hand.next(), in_hand.next()) {
+            self.award_uncontested(winner);
+            self.update_stats(&action);
+            self.refresh_action_deadline();
+            self.check_chip_conservation(chips_before, "a player action");
+            return Ok(());
+        }
+
+        // Move to the next player who still has a decision to make
+        self.current_player = self.next_seat_to_act(seat);
+
+        // Robots stand pat in five-card draw as soon as the betting before the draw is over.
+        if self.variant == Variant::FiveCardDraw
+            && self.game_mode == GameMode::RobotPlay
+            && self.street() == GamePhase::PreFlop
+            && self.betting_round_complete()
+        {
+            for robot in 0..self.players.len() {
+                if self.players[robot].is_robot && !self.players[robot].cards.is_empty() && !self.drawn.contains(&robot) {
+                    self.draw(robot, &[])?;
+                }
+            }
         }
-        
+
         self.update_stats(&action);
-        
+        self.refresh_action_deadline();
+        self.check_chip_conservation(chips_before, "a player action");
+
+        Ok(())
+    }
+
+    /// Robots take their turns on their own only against a human in RobotPlay. A Simulation
+    /// hand is stepped through for study, so each action waits to be made or `/auto-play`ed.
+    /// With a think delay, robot turns are played out by `play_robot_turns` instead.
+    fn robots_auto_act(&self) -> bool {
+        self.game_mode == GameMode::RobotPlay && self.robot_delay_ms.is_none()
+    }
+
+    /// Whether a robot with a think delay is waiting to take its turn.
+    fn robot_to_act(&self) -> bool {
+        self.game_mode == GameMode::RobotPlay
+            && self.robot_delay_ms.is_some()
+            && self.players[self.current_player].is_robot
+            && !self.at_showdown()
+            && !self.betting_round_complete()
+    }
+
+    /// Whether the current betting round is over: everyone who can still act has matched the bet
+    /// and acted since the last bet or raise. Blinds don't count as acting, so the big blind
+    /// keeps their option.
+    fn betting_round_complete(&self) -> bool {
+        if self.players.iter().filter(|p| !p.cards.is_empty()).count() <= 1 {
+            return true;
+        }
+        let street = self.street();
+        let actions: Vec<&Action> = self
+            .hand_history
+            .last()
+            .map(|hand| hand.actions.iter().filter(|a| a.phase == street).map(|a| &a.action).collect())
+            .unwrap_or_default();
+        let last_aggression = actions
+            .iter()
+            .rposition(|a| matches!(a.action_type, ActionType::Bet | ActionType::Raise));
+
+        let able: Vec<usize> = (0..self.players.len()).filter(|&i| self.players[i].can_act()).collect();
+        if let [only] = able[..] {
+            // With everyone else all-in, a lone player only has a decision if they owe chips.
+            return self.players[only].current_bet >= self.current_bet;
+        }
+        able.iter().all(|&seat| {
+            let last_acted = actions.iter().rposition(|a| a.player_index == seat);
+            self.players[seat].current_bet == self.current_bet
+                && match (last_acted, last_aggression) {
+                    (Some(acted), Some(aggression)) => acted >= aggression,
+                    (acted, None) => acted.is_some(),
+                    (None, Some(_)) => false,
+                }
+        })
+    }
+
+    /// Plays the rest of a Simulation hand with every seat acting as a robot: each betting round
+    /// runs to completion before the next street is dealt, and the hand ends at showdown.
+    /// Returns the winning seats.
+    fn auto_play(&mut self) -> Result<Vec<usize>, GameError> {
+        if self.game_mode != GameMode::Simulation {
+            return Err("Auto-play is only available in Simulation mode".into());
+        }
+        if self.at_showdown() {
+            return Err("The hand is already over".into());
+        }
+
+        loop {
+            // Everyone else folded and the pot has already been awarded.
+            if self.at_showdown() {
+                return Ok(self.hand_history.last().and_then(|hand| hand.winner).into_iter().collect());
+            }
+            if self.betting_round_complete() {
+                // Auto-played five-card draw hands stand pat, then bet again.
+                if self.variant == Variant::FiveCardDraw && !self.draw_complete() {
+                    for seat in 0..self.players.len() {
+                        if !self.players[seat].cards.is_empty() && !self.drawn.contains(&seat) {
+                            self.draw(seat, &[])?;
+                        }
+                    }
+                    continue;
+                }
+                match self.community_cards.len() {
+                    dealt if dealt >= self.variant.board_cards() => return self.showdown(),
+                    0 => self.deal_flop(),
+                    3 => self.deal_turn(),
+                    4 => self.deal_river(),
+                    _ => return self.showdown(),
+                }
+                continue;
+            }
+            let action = self.robot_decision(self.current_player)?;
+            self.handle_action(action)?;
+        }
+    }
+
+    /// Starts the action clock when a human still in the hand is up in a RobotPlay game with an
+    /// action timeout, and clears it otherwise.
+    fn refresh_action_deadline(&mut self) {
+        let player = &self.players[self.current_player];
+        self.action_deadline = match self.action_timeout_secs {
+            Some(secs) if self.game_mode == GameMode::RobotPlay && !player.is_robot && !player.cards.is_empty() => {
+                Some(chrono::Utc::now() + chrono::Duration::seconds(secs as i64))
+            },
+            _ => None,
+        };
+    }
+
+    /// Acts for the current player once their deadline has passed: a check when that's legal,
+    /// otherwise a fold. Time past the deadline comes out of the player's time bank first, and
+    /// a player who drains their bank is folded. The action goes through `handle_action` like
+    /// any other.
+    fn enforce_action_deadline(&mut self, now: chrono::DateTime<chrono::Utc>) -> Result<Option<Action>, GameError> {
+        let deadline = match self.action_deadline {
+            Some(deadline) if now >= deadline => deadline,
+            _ => return Ok(None),
+        };
+
+        let seat = self.current_player;
+        let banked = self.players[seat].time_bank_secs;
+        if banked > 0 {
+            let overrun = ((now - deadline).num_seconds().max(1) as u32).min(banked);
+            self.players[seat].time_bank_secs -= overrun;
+            self.action_deadline = Some(deadline + chrono::Duration::seconds(overrun as i64));
+            if self.players[seat].time_bank_secs > 0 {
+                return Ok(None);
+            }
+        }
+        let bank_drained = self.time_bank_secs > 0 && self.players[seat].time_bank_secs == 0;
+
+        let action_type = if !bank_drained && self.legal_actions(seat)?.actions.contains(&ActionType::Check) {
+            ActionType::Check
+        } else {
+            ActionType::Fold
+        };
+        let action = Action { player_index: seat, action_type, amount: None };
+        self.handle_action(action.clone())?;
+        self.advance_until_human()?;
+        Ok(Some(action))
+    }
+
+    /// Lets robots take their turns one after another, when they auto-act, until a human is up,
+    /// the betting round is over or the hand is settled. A loop rather than recursion keeps the
+    /// stack flat however many robots sit at the table.
+    fn advance_until_human(&mut self) -> Result<(), GameError> {
+        while self.robots_auto_act()
+            && !self.at_showdown()
+            && self.players[self.current_player].is_robot
+            && !self.betting_round_complete()
+        {
+            let seat = self.current_player;
+            self.handle_robot_action()?;
+            // When nobody else can act the turn stays put, and the robot isn't asked again.
+            if self.current_player == seat {
+                break;
+            }
+        }
         Ok(())
     }
 
-    fn handle_robot_action(&mut self) -> Result<(), String> {
-        let robot = &self.players[self.current_player];
+    /// Takes the current robot's turn, if it's a robot's turn at all. Robots after it wait.
+    fn handle_robot_action(&mut self) -> Result<(), GameError> {
+        let seat = self.current_player;
+        let robot = &self.players[seat];
         if !robot.is_robot {
             return Ok(());
         }
+        // A robot that has folded or is all-in has nothing to decide; pass the turn on.
+        if !robot.can_act() {
+            self.current_player = self.next_seat_to_act(seat);
+            return Ok(());
+        }
 
-        let personality = self.get_robot_personality();
-        let mut rng = rand::thread_rng();
-        
-        let action = if self.current_bet == 0 {
-            if rng.gen::<f64>() < (1.0 - personality.aggression) {
-                Action {
-                    player_index: self.current_player,
-                    action_type: ActionType::Check,
-                    amount: None,
-                }
+        self.take_robot_turn(seat).map(|_| ())
+    }
+
+    /// Plays exactly one decision for the robot whose turn it is, leaving any robots after it
+    /// waiting, so robot play can be stepped through when debugging.
+    fn step_robot(&mut self) -> Result<Action, GameError> {
+        if self.at_showdown() {
+            return Err(GameError::HandOver);
+        }
+        let seat = self.current_player;
+        if !self.players[seat].is_robot || !self.players[seat].can_act() || self.betting_round_complete() {
+            return Err(GameError::NoRobotToAct);
+        }
+        self.take_robot_turn(seat)
+    }
+
+    /// Decides and applies the robot in `seat`'s action, noting its tell.
+    fn take_robot_turn(&mut self, seat: usize) -> Result<Action, GameError> {
+        let action = self.robot_decision(seat)?;
+        // The tell reflects the robot's real equity, not the estimate it acted on.
+        let strong = self.players[seat].win_probability >= 0.5;
+        let recorded = self.hand_history.last().map_or(0, |hand| hand.actions.len());
+        let seed = (self.hand_history.len() as u64) << 32 | recorded as u64;
+        let tell = self.players[seat]
+            .personality
+            .as_ref()
+            .and_then(|personality| robot_tell(personality, &action.action_type, strong, seed));
+        self.handle_action(action.clone())?;
+        if let Some(entry) = self.hand_history.last_mut().and_then(|hand| hand.actions.get_mut(recorded)) {
+            entry.tell = tell;
+        }
+        Ok(action)
+    }
+
+    /// Chooses an action for the player in `seat` the way a robot would, from its personality
+    /// and its own estimate of its equity.
+    fn robot_decision(&self, seat: usize) -> Result<Action, GameError> {
+        let personality = self.get_robot_personality(seat);
+        let legal = self.legal_actions(seat)?;
+        let mut rng = self.game_rng();
+        let equity = self.robot_equity(seat, &mut rng);
+        // With a better-than-even share of the pot the robot plays for value.
+        let in_hand = self.players.iter().filter(|p| !p.cards.is_empty()).count();
+        let strong = equity > 1.0 / in_hand as f64;
+        // Bet into an unopened pot, raise over an existing bet.
+        let aggressive_action = if self.current_bet == 0 { ActionType::Bet } else { ActionType::Raise };
+        let passive = |action_type| Action { player_index: seat, action_type, amount: None };
+
+        // A short stack plays push/fold: all-in when shoving beats folding, otherwise out.
+        let stack = self.players[seat].chips;
+        if stack < self.big_blind.saturating_mul(SHORT_STACK_BIG_BLINDS) {
+            let can_raise = legal.actions.iter().any(|a| matches!(a, ActionType::Bet | ActionType::Raise));
+            let action = match push_fold(stack, self.pot, equity) {
+                PushFold::Shove if can_raise => Action {
+                    player_index: seat,
+                    action_type: aggressive_action,
+                    amount: Some(legal.max_raise),
+                },
+                PushFold::Shove if legal.actions.contains(&ActionType::Call) => passive(ActionType::Call),
+                _ if legal.actions.contains(&ActionType::Check) => passive(ActionType::Check),
+                _ => passive(ActionType::Fold),
+            };
+            return Ok(action);
+        }
+
+        let action = if legal.actions.contains(&ActionType::Check) {
+            let bet_amount = (self.pot as f64 * personality.aggression * 0.5) as u32;
+            let bets = if strong {
+                rng.gen::<f64>() < personality.aggression
             } else {
-                let bet_amount = (self.pot as f64 * personality.aggression * 0.5) as u32;
-                Action {
-                    player_index: self.current_player,
-                    action_type: ActionType::Bet,
-                    amount: Some(bet_amount),
-                }
+                rng.gen::<f64>() < personality.bluff_frequency
+            };
+            match robot_bet_size(&legal, bet_amount) {
+                Some(amount) if bets => Action {
+                    player_index: seat,
+                    action_type: aggressive_action,
+                    amount: Some(amount),
+                },
+                _ => passive(ActionType::Check),
             }
         } else {
-            let r = rng.gen::<f64>();
-            if r < (1.0 - personality.aggression) * 0.5 {
-                Action {
-                    player_index: self.current_player,
-                    action_type: ActionType::Fold,
-                    amount: None,
-                }
-            } else if r < (1.0 - personality.aggression) {
+            // Calling is only worth it when the robot's equity beats the price it's being laid.
+            let pot_odds = pot_odds(legal.call_amount, self.pot);
+            let raise_amount = (self.current_bet as f64 * (1.0 + personality.aggression)) as u32;
+            if equity < pot_odds {
+                passive(ActionType::Fold)
+            } else if !strong || rng.gen::<f64>() >= personality.aggression {
+                passive(ActionType::Call)
+            } else if let Some(amount) = robot_bet_size(&legal, raise_amount) {
                 Action {
-                    player_index: self.current_player,
-                    action_type: ActionType::Call,
-                    amount: None,
+                    player_index: seat,
+                    action_type: aggressive_action,
+                    amount: Some(amount),
                 }
             } else {
-                let raise_amount = (self.current_bet as f64 * (1.0 + personality.aggression)) as u32;
-                Action {
-                    player_index: self.current_player,
-                    action_type: ActionType::Raise,
-                    amount: Some(raise_amount),
-                }
+                passive(ActionType::Call)
             }
         };
+        Ok(action)
+    }
 
-        self.handle_action(action)
-    }
-
-    fn get_robot_personality(&self) -> RobotPersonality {
-        let personalities = vec![
-            RobotPersonality {
-                name: "PokerBot 3000".to_string(),
-                emoji: "🤖".to_string(),
-                style: "Calculating".to_string(),
-                description: "A cold, calculating machine that plays by the numbers".to_string(),
-                aggression: 0.7,
-                bluff_frequency: 0.3,
-                patience: 0.8,
-                risk_tolerance: 0.6,
-            },
-            RobotPersonality {
-                name: "Lucky Larry".to_string(),
-                emoji: "🍀".to_string(),
-                style: "Lucky".to_string(),
-                description: "Always seems to get the cards he needs".to_string(),
-                aggression: 0.5,
-                bluff_frequency: 0.6,
-                patience: 0.4,
-                risk_tolerance: 0.8,
-            },
-            RobotPersonality {
-                name: "Bluff Master".to_string(),
-                emoji: "🎭".to_string(),
-                style: "Deceptive".to_string(),
-                description: "Loves to bluff and keep you guessing".to_string(),
-                aggression: 0.8,
-                bluff_frequency: 0.8,
-                patience: 0.3,
-                risk_tolerance: 0.9,
-            },
-            RobotPersonality {
-                name: "Safe Sally".to_string(),
-                emoji: "🛡️".to_string(),
-                style: "Conservative".to_string(),
-                description: "Plays it safe and waits for good hands".to_string(),
-                aggression: 0.3,
-                bluff_frequency: 0.2,
-                patience: 0.9,
-                risk_tolerance: 0.3,
-            },
-        ];
+    /// The robot in `seat`'s own estimate of its equity, from its cards and the board alone.
+    /// Harder robots run more simulations; easier ones misjudge it by up to ±0.3.
+    fn robot_equity(&self, seat: usize, rng: &mut impl Rng) -> f64 {
+        let robot = &self.players[seat];
+        let mut unseen = self.deck.clone();
+        let mut opponents = 0;
+        for (i, player) in self.players.iter().enumerate() {
+            if i != seat && !player.cards.is_empty() {
+                unseen.extend_from_slice(&player.cards);
+                opponents += 1;
+            }
+        }
+
+        let sims = 100 + (self.difficulty * 900.0) as usize;
+        let estimate = simulate_win_probability_hidden_with(
+            &robot.cards,
+            opponents,
+            &self.community_cards,
+            &unseen,
+            &[],
+            sims,
+            self.variant,
+            rng,
+        );
+        let noise = (1.0 - self.difficulty) * 0.3;
+        (estimate + rng.gen_range(-noise..=noise)).clamp(0.0, 1.0)
+    }
 
-        personalities[self.current_player % personalities.len()].clone()
+    /// Randomness for the next shuffle or robot decision. A seeded game derives it from the seed,
+    /// the hand number and the actions taken so far, so the same seed plays out the same way.
+    fn game_rng(&self) -> StdRng {
+        let Some(seed) = self.seed else {
+            return StdRng::from_entropy();
+        };
+        let hand = self.hand_history.len() as u64;
+        let recorded = self.hand_history.last().map_or(0, |hand| hand.actions.len()) as u64;
+        StdRng::seed_from_u64(seed ^ (hand << 32 | recorded).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    /// The personality the robot in `seat` plays with, as shown at the table. A human seat
+    /// auto-played in Simulation mode plays like the robot that would have sat there.
+    fn get_robot_personality(&self, seat: usize) -> RobotPersonality {
+        self.players[seat].personality.clone().unwrap_or_else(|| {
+            let personalities = robot_personalities();
+            personalities[seat % personalities.len()].clone()
+        })
     }
 
     fn update_stats(&mut self, action: &Action) {
-        if let Some(player) = self.players.get_mut(action.player_index) {
-            let stats = &mut self.stats.players[action.player_index];
-            stats.games_played += 1;
-            
+        if let Some(stats) = self.stats.players.get_mut(action.player_index) {
             match action.action_type {
                 ActionType::Bet | ActionType::Raise => {
                     stats.favorite_action = "Aggressive".to_string();
@@ -712,18 +3316,495 @@ impl GameState {
         }
     }
 
-    fn record_action(&mut self, action: &Action) {
-        if let Some(current_hand) = self.hand_history.last_mut() {
-            current_hand.actions.push(action.clone());
-            current_hand.pot_size = self.pot;
-            current_hand.community_cards = self.community_cards.clone();
-            current_hand.player_cards = self.players.iter()
-                .map(|p| p.cards.clone())
-                .collect();
+    /// Hands each seat its `winnings` from the pot, crediting `winners` with the win, and settles
+    /// everyone's profit for the hand.
+    fn pay_out(&mut self, winnings: &[u32], winners: &[usize]) {
+        for &w in winners {
+            self.players[w].chips += winnings[w];
+            self.stats.players[w].games_won += 1;
         }
-    }
+
+        for (i, player) in self.players.iter_mut().enumerate() {
+            self.stats.players[i].total_profit += winnings[i] as i32 - player.total_bet as i32;
+            player.current_bet = 0;
+            player.total_bet = 0;
+        }
+
+        self.record_completed_pot(self.pot);
+    }
+
+    /// The house's cut of a showdown pot of `pot` chips: `rake_percent` of it, rounded down and
+    /// held to `rake_cap` when there is one.
+    fn rake_for(&self, pot: u32) -> u32 {
+        let rake = (f64::from(pot) * self.rake_percent).floor() as u32;
+        if self.rake_cap > 0 { rake.min(self.rake_cap) } else { rake }
+    }
+
+    /// Awards the pot to `winner`, the last player left after everyone else folded. The board
+    /// isn't dealt and no hand is shown, so the rest of the deck is still there to rabbit hunt.
+    fn award_uncontested(&mut self, winner: usize) {
+        let chips_before = self.total_chips();
+        let mut winnings = vec![0u32; self.players.len()];
+        winnings[winner] = self.pot;
+        self.pay_out(&winnings, &[winner]);
+
+        if let Some(current_hand) = self.hand_history.last_mut() {
+            current_hand.phase = GamePhase::Showdown;
+            current_hand.pot_size = self.pot;
+            current_hand.winner = Some(winner);
+        }
+
+        self.pot = 0;
+        self.current_bet = 0;
+        self.last_raise = 0;
+        self.check_chip_conservation(chips_before, "an uncontested pot");
+        self.check_game_over();
+    }
+
+    /// Splits the pot into the main pot and any side pots, each with the seats still in the hand
+    /// that can win it: nobody wins more from each opponent than they put in themselves. Chips
+    /// from folded players sit in whichever pots they reached.
+    fn side_pots(&self) -> Vec<(u32, Vec<usize>)> {
+        let contenders: Vec<usize> = (0..self.players.len()).filter(|&i| !self.players[i].cards.is_empty()).collect();
+        let mut levels: Vec<u32> = contenders.iter().map(|&i| self.players[i].total_bet).collect();
+        levels.sort_unstable();
+        levels.dedup();
+
+        let mut pots = Vec::new();
+        let mut previous = 0;
+        for (k, &level) in levels.iter().enumerate() {
+            // The last pot also takes anything folded players put in beyond the biggest contender.
+            let cap = if k + 1 == levels.len() { u32::MAX } else { level };
+            let amount: u32 = self.players.iter().map(|p| p.total_bet.clamp(previous, cap) - previous).sum();
+            let eligible = contenders.iter().copied().filter(|&i| self.players[i].total_bet >= level).collect();
+            if amount > 0 {
+                pots.push((amount, eligible));
+            }
+            previous = level;
+        }
+
+        // Bets that don't add up to the pot can't be split fairly; it all goes to the main pot.
+        if pots.iter().map(|(amount, _)| amount).sum::<u32>() != self.pot || pots.is_empty() {
+            return vec![(self.pot, contenders)];
+        }
+        pots
+    }
+
+    /// Resolves the hand at showdown: completes the board, awards the pot to the best hand(s)
+    /// and updates each player's win count and profit. Returns the winning seats.
+    fn showdown(&mut self) -> Result<Vec<usize>, GameError> {
+        self.resolve_showdown(1)
+    }
+
+    /// Resolves an all-in hand by dealing the rest of the board twice, with half the pot going
+    /// to the best hand(s) on each run. Needs cards still to come and nobody left to bet.
+    fn showdown_run_twice(&mut self) -> Result<Vec<usize>, GameError> {
+        let in_hand: Vec<&Player> = self.players.iter().filter(|p| !p.cards.is_empty()).collect();
+        let can_still_bet = in_hand.iter().filter(|p| p.can_act()).count();
+        let all_in_showdown = in_hand.len() >= 2 && can_still_bet <= 1 && self.betting_round_complete();
+        if self.community_cards.len() >= self.variant.board_cards() || !all_in_showdown {
+            return Err("Running it twice needs every remaining player all-in with cards to come".into());
+        }
+        self.resolve_showdown(2)
+    }
+
+    /// Deals `runs` completions of the board and splits the pot evenly between them. The first
+    /// board becomes the community cards; a second is kept in the hand history.
+    fn resolve_showdown(&mut self, runs: usize) -> Result<Vec<usize>, GameError> {
+        if self.at_showdown() {
+            return Err(GameError::HandOver);
+        }
+        if self.variant == Variant::FiveCardDraw && !(self.draw_complete() && self.betting_round_complete()) {
+            return Err("The draw and the betting after it must finish before the showdown".into());
+        }
+        let chips_before = self.total_chips();
+        let missing = self.variant.board_cards().saturating_sub(self.community_cards.len());
+        if self.deck.len() < missing * runs {
+            return Err("Not enough cards to complete the board".into());
+        }
+        let boards: Vec<Vec<Card>> = (0..runs)
+            .map(|_| {
+                let mut board = self.community_cards.clone();
+                board.extend((0..missing).filter_map(|_| self.deck.pop()));
+                board
+            })
+            .collect();
+        // Only the first run becomes the board; the rest are kept in the hand history.
+        for board in &boards[1..] {
+            self.discards.extend_from_slice(&board[self.community_cards.len()..]);
+        }
+
+        let all_in: Vec<bool> = self.players.iter().map(|p| p.is_all_in()).collect();
+
+        let mut pots = self.side_pots();
+        if pots.iter().all(|(_, eligible)| eligible.is_empty()) {
+            return Err("No players left in the hand".into());
+        }
+        // The rake leaves play before anyone is paid, coming out of the main pot first.
+        let rake = self.rake_for(self.pot);
+        let mut unraked = rake;
+        for (amount, _) in &mut pots {
+            let taken = unraked.min(*amount);
+            *amount -= taken;
+            unraked -= taken;
+        }
+        self.stats.total_rake += u64::from(rake);
+        let seats = self.players.len();
+        let mut winnings = vec![0u32; seats];
+        let mut winners: Vec<usize> = Vec::new();
+        let mut first_run_hands: Vec<(usize, Hand)> = Vec::new();
+        for (run, board) in boards.iter().enumerate() {
+            let contenders: Vec<(usize, Hand)> = self.players
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| !p.cards.is_empty())
+                .filter_map(|(i, p)| Some((i, self.variant.best_hand(&p.cards, board)?)))
+                .collect();
+            for (amount, eligible) in &pots {
+                // Each run plays for an equal part of every pot; an odd chip goes to the first run.
+                let part = amount / runs as u32 + if run == 0 { amount % runs as u32 } else { 0 };
+                let hands = contenders.iter().filter(|(i, _)| eligible.contains(i));
+                let Some(best) = hands.clone().map(|(_, hand)| hand).max() else { continue };
+                let mut pot_winners: Vec<usize> = hands.filter(|(_, hand)| hand == best).map(|&(i, _)| i).collect();
+                // Odd chips go to the winners closest to the dealer's left.
+                pot_winners.sort_by_key(|&w| (w + seats - self.dealer - 1) % seats);
+
+                let share = part / pot_winners.len() as u32;
+                let remainder = (part % pot_winners.len() as u32) as usize;
+                for (k, &w) in pot_winners.iter().enumerate() {
+                    winnings[w] += share + u32::from(k < remainder);
+                    if !winners.contains(&w) {
+                        winners.push(w);
+                    }
+                }
+            }
+            if run == 0 {
+                first_run_hands = contenders;
+            }
+        }
+        winners.sort_unstable();
+
+        self.pay_out(&winnings, &winners);
+        self.community_cards = boards[0].clone();
+
+        if let Some(current_hand) = self.hand_history.last_mut() {
+            current_hand.phase = GamePhase::Showdown;
+            current_hand.pot_size = self.pot;
+            current_hand.rake = rake;
+            current_hand.community_cards = self.community_cards.clone();
+            current_hand.second_board = boards.get(1).cloned().unwrap_or_default();
+            current_hand.winner = winners.first().copied();
+            // The winning hands are always shown; everyone else chooses to show or muck.
+            current_hand.revealed = winners.clone();
+            // Hands are described on the first board; winnings cover every run.
+            current_hand.showdown_results = first_run_hands
+                .iter()
+                .map(|(i, hand)| ShowdownResult {
+                    player_index: *i,
+                    hand_type: hand.hand_type.clone(),
+                    description: describe_hand(hand),
+                    amount_won: winnings[*i],
+                    all_in: all_in[*i],
+                    best_cards: self.variant
+                        .best_hand_cards(&self.players[*i].cards, &boards[0])
+                        .map(|(_, cards)| cards)
+                        .unwrap_or_default(),
+                })
+                .collect();
+        }
+
+        self.pot = 0;
+        self.current_bet = 0;
+        self.last_raise = 0;
+        self.check_chip_conservation(chips_before - u64::from(rake), "the showdown payout");
+        self.check_game_over();
+        Ok(winners)
+    }
+
+    /// Players ranked by profit, with wins breaking ties. Win rate is 0 before any hands.
+    fn leaderboard(&self) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = self
+            .players
+            .iter()
+            .zip(&self.stats.players)
+            .map(|(player, stats)| LeaderboardEntry {
+                name: player.name.clone(),
+                chips: player.chips,
+                total_profit: stats.total_profit,
+                games_won: stats.games_won,
+                games_played: stats.games_played,
+                win_rate: if stats.games_played == 0 {
+                    0.0
+                } else {
+                    stats.games_won as f64 / stats.games_played as f64
+                },
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.total_profit
+                .cmp(&a.total_profit)
+                .then(b.games_won.cmp(&a.games_won))
+        });
+        entries
+    }
+
+    /// Rebuilds recorded hand `hand_index` as it stood once its first `action_index` actions had
+    /// been taken; 0 is the hand as dealt, after the blinds.
+    fn replay(&self, hand_index: usize, action_index: usize) -> Result<ReplaySnapshot, GameError> {
+        let hand = self.hand_history.get(hand_index).ok_or(GameError::NoSuchHand { index: hand_index })?;
+        if action_index > hand.actions.len() {
+            return Err(GameError::NoSuchAction { index: action_index, recorded: hand.actions.len() });
+        }
+        let actions = hand.actions[..action_index].to_vec();
+        let (phase, pot) = match actions.last() {
+            Some(last) => (last.phase.clone(), last.pot),
+            None => (GamePhase::PreFlop, hand.streets.first().map_or(0, |street| street.pot)),
+        };
+        // The board is whatever had been dealt by the street the last action was taken on.
+        let community_cards = match hand.streets.iter().find(|street| street.phase == phase) {
+            Some(street) => street.community_cards.clone(),
+            None if phase == GamePhase::Showdown => hand.community_cards.clone(),
+            None => Vec::new(),
+        };
+        Ok(ReplaySnapshot { hand_index, action_index, phase, community_cards, pot, actions })
+    }
+
+    /// The community cards that would have come, peeked from the top of the deck without
+    /// dealing them. Only available once the hand has been won without a showdown, since
+    /// mid-hand it would reveal the cards still to come.
+    fn rabbit_hunt(&self) -> Result<Vec<Card>, GameError> {
+        let in_hand = self.players.iter().filter(|p| !p.cards.is_empty()).count();
+        if in_hand > 1 {
+            return Err(GameError::HandStillInPlay);
+        }
+        let missing = self.variant.board_cards().saturating_sub(self.community_cards.len());
+        // Cards are dealt with `pop`, so the top of the deck is its end.
+        Ok(self.deck.iter().rev().take(missing).copied().collect())
+    }
+
+    /// Whether the current hand has been resolved at showdown.
+    fn at_showdown(&self) -> bool {
+        self.hand_history.last().is_some_and(|hand| hand.phase == GamePhase::Showdown)
+    }
+
+    /// Who sits where, the button and blinds, and whose turn it is. Nobody is to act once the
+    /// betting round is over or the hand has been settled.
+    fn table_view(&self) -> TableView {
+        let waiting = !self.at_showdown() && !self.betting_round_complete();
+        let seats = self.players
+            .iter()
+            .enumerate()
+            .map(|(seat, p)| SeatView {
+                seat,
+                name: p.name.clone(),
+                chips: p.chips,
+                current_bet: p.current_bet,
+                is_robot: p.is_robot,
+                folded: p.cards.is_empty(),
+                all_in: p.is_all_in(),
+                to_act: waiting && seat == self.current_player && p.can_act(),
+            })
+            .collect();
+        TableView {
+            seats,
+            dealer: self.dealer,
+            small_blind_seat: self.blind_seats.map(|(small, _)| small),
+            big_blind_seat: self.blind_seats.map(|(_, big)| big),
+        }
+    }
+
+    /// What the human in seat 0 currently sees of the players, pot, board and phase.
+    fn snapshot(&self) -> StateSnapshot {
+        let view = self.human_view();
+        StateSnapshot {
+            version: self.version,
+            players: view.players,
+            pot: self.pot,
+            community_cards: view.community_cards,
+            phase: if self.at_showdown() { GamePhase::Showdown } else { self.street() },
+            current_player: self.current_player,
+            current_bet: self.current_bet,
+        }
+    }
+
+    /// Moves to the next version and remembers what it looked like, so clients can later ask
+    /// for just what has changed since.
+    fn bump_version(&mut self) {
+        self.version += 1;
+        if self.snapshots.len() == MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        let snapshot = self.snapshot();
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// The fields that differ between the client's `since` version and now. A version no
+    /// longer remembered gets every field.
+    fn diff_since(&self, since: u64) -> StateDiff {
+        let now = self.snapshot();
+        let before = self.snapshots.iter().find(|s| s.version == since);
+        let changed = |unchanged: fn(&StateSnapshot, &StateSnapshot) -> bool| {
+            before.is_none_or(|before| !unchanged(before, &now))
+        };
+        StateDiff {
+            version: self.version,
+            full: before.is_none(),
+            players: now.players
+                .iter()
+                .enumerate()
+                .filter(|&(seat, p)| before.and_then(|b| b.players.get(seat)) != Some(p))
+                .map(|(seat, p)| (seat, p.clone()))
+                .collect(),
+            pot: changed(|b, n| b.pot == n.pot).then_some(now.pot),
+            community_cards: changed(|b, n| b.community_cards == n.community_cards).then(|| now.community_cards.clone()),
+            phase: changed(|b, n| b.phase == n.phase).then(|| now.phase.clone()),
+            current_player: changed(|b, n| b.current_player == n.current_player).then_some(now.current_player),
+            current_bet: changed(|b, n| b.current_bet == n.current_bet).then_some(now.current_bet),
+        }
+    }
+
+    /// The state including the deck, which plain serialization leaves out.
+    fn with_deck(&self) -> FullGameState {
+        FullGameState { game: self.clone(), deck: self.deck.clone() }
+    }
+
+    /// The state as a spectator sees it: hole cards stay hidden until the showdown unless
+    /// `reveal` is set.
+    fn spectator_view(&self, reveal: bool) -> GameState {
+        if reveal {
+            self.clone()
+        } else {
+            self.with_hole_cards_hidden(|_| true)
+        }
+    }
+
+    /// The state as the player in `player_index` may see it: everyone else's hole cards stay
+    /// hidden until the showdown.
+    fn redacted_for(&self, player_index: usize) -> GameState {
+        self.with_hole_cards_hidden(|seat| seat != player_index)
+    }
+
+    /// The state sent back to the human in seat 0.
+    fn human_view(&self) -> GameState {
+        self.human_view_for(0)
+    }
+
+    /// The state sent back to the human in `seat`. Against robots only that player's own cards
+    /// are shown; Simulation mode is for watching every hand, so nothing is hidden there. With
+    /// `show_probabilities` off every player's equity is zeroed as well.
+    fn human_view_for(&self, seat: usize) -> GameState {
+        let mut view = match self.game_mode {
+            GameMode::RobotPlay => self.redacted_for(seat),
+            GameMode::Simulation => self.clone(),
+        };
+        if !self.show_probabilities {
+            for player in &mut view.players {
+                player.win_probability = 0.0;
+                player.equity_history.clear();
+            }
+        }
+        view
+    }
+
+    /// The hand history as the human in seat 0 may see it. Simulation mode shows every hand while
+    /// it's played, but once a hand is over the ones mucked at showdown stay hidden there too.
+    fn history_view(&self) -> Vec<HandHistory> {
+        let mut history = self.human_view().hand_history;
+        let redacted = self.redacted_for(0).hand_history;
+        for (hand, redacted) in history.iter_mut().zip(redacted) {
+            if redacted.phase == GamePhase::Showdown {
+                hand.player_cards = redacted.player_cards;
+                hand.showdown_results = redacted.showdown_results;
+            }
+        }
+        history
+    }
+
+    /// Clones the state with the hole cards of every seat matching `hidden` removed, both at the
+    /// table and in the hand history. Hands shown at showdown stay visible; mucked ones don't.
+    /// The deck is dropped too, since the unseen cards it leaves out would give the hidden
+    /// hands away.
+    fn with_hole_cards_hidden(&self, hidden: impl Fn(usize) -> bool) -> GameState {
+        let mut view = self.clone();
+        let shown_at_showdown = |hand: &HandHistory| {
+            if hand.phase == GamePhase::Showdown { hand.revealed.clone() } else { Vec::new() }
+        };
+
+        let revealed = self.hand_history.last().map(shown_at_showdown).unwrap_or_default();
+        for (seat, player) in view.players.iter_mut().enumerate() {
+            if hidden(seat) && !revealed.contains(&seat) {
+                player.cards.clear();
+            }
+        }
+        for hand in &mut view.hand_history {
+            let revealed = shown_at_showdown(hand);
+            for (seat, cards) in hand.player_cards.iter_mut().enumerate() {
+                if hidden(seat) && !revealed.contains(&seat) {
+                    cards.clear();
+                }
+            }
+            // A mucked hand's result would say what it was.
+            hand.showdown_results.retain(|r| !hidden(r.player_index) || revealed.contains(&r.player_index));
+        }
+        view.deck.clear();
+        view.discards.clear();
+        view
+    }
+
+    /// Folds a finished hand's pot into the session-wide hand count, average and biggest pot.
+    fn record_completed_pot(&mut self, pot: u32) {
+        let stats = &mut self.stats;
+        stats.total_hands += 1;
+        stats.total_pot += u64::from(pot);
+        stats.average_pot = (stats.total_pot / u64::from(stats.total_hands)) as u32;
+        stats.biggest_pot = stats.biggest_pot.max(pot);
+    }
+
+    fn record_action(&mut self, action: &Action) {
+        let phase = match action.action_type {
+            ActionType::Show | ActionType::Muck => GamePhase::Showdown,
+            _ => self.street(),
+        };
+        if let Some(current_hand) = self.hand_history.last_mut() {
+            // Never before the previous action, even if the clock steps back.
+            let previous = current_hand.actions.last().map_or(current_hand.timestamp, |a| a.timestamp);
+            let timestamp = chrono::Utc::now().max(previous);
+            current_hand.actions.push(RecordedAction {
+                action: action.clone(),
+                phase: phase.clone(),
+                pot: self.pot,
+                tell: None,
+                timestamp,
+                elapsed_ms: (timestamp - previous).num_milliseconds().unsigned_abs(),
+            });
+            current_hand.phase = phase;
+            current_hand.pot_size = self.pot;
+            current_hand.community_cards = self.community_cards.clone();
+            current_hand.player_cards = self.players.iter()
+                .map(|p| p.cards.clone())
+                .collect();
+        }
+    }
     
+    /// Snapshots the board and pot as a betting round opens, so a replay can step through
+    /// the hand street by street.
+    fn record_street(&mut self) {
+        let snapshot = StreetSnapshot {
+            phase: self.street(),
+            community_cards: self.community_cards.clone(),
+            pot: self.pot,
+        };
+        if let Some(current_hand) = self.hand_history.last_mut() {
+            current_hand.streets.push(snapshot);
+        }
+    }
+
     fn start_new_hand(&mut self) {
+        for (player, stats) in self.players.iter().zip(self.stats.players.iter_mut()) {
+            if !player.cards.is_empty() {
+                stats.games_played += 1;
+            }
+        }
         self.hand_history.push(HandHistory {
             timestamp: chrono::Utc::now(),
             phase: GamePhase::PreFlop,
@@ -732,6 +3813,14 @@ impl GameState {
             community_cards: Vec::new(),
             player_cards: Vec::new(),
             winner: None,
+            revealed: Vec::new(),
+            showdown_results: Vec::new(),
+            streets: Vec::new(),
+            second_board: Vec::new(),
+            dealer: self.dealer,
+            starting_stacks: self.players.iter().map(|p| p.chips).collect(),
+            posts: Vec::new(),
+            rake: 0,
         });
     }
 }
@@ -740,54 +3829,442 @@ impl GameState {
 pub struct HandHistory {
     timestamp: chrono::DateTime<chrono::Utc>,
     phase: GamePhase,
-    actions: Vec<Action>,
+    actions: Vec<RecordedAction>,
     pot_size: u32,
     community_cards: Vec<Card>,
     player_cards: Vec<Vec<Card>>,
     winner: Option<usize>,
+    #[serde(default)]
+    revealed: Vec<usize>,  // Seats whose cards were shown at showdown, winners first
+    #[serde(default)]
+    showdown_results: Vec<ShowdownResult>,
+    #[serde(default)]
+    streets: Vec<StreetSnapshot>,  // Board and pot as each betting round opened
+    #[serde(default)]
+    second_board: Vec<Card>,  // The board's second run when the hand was run twice
+    #[serde(default)]
+    dealer: usize,
+    #[serde(default)]
+    starting_stacks: Vec<u32>,  // Each seat's chips before the antes and blinds
+    #[serde(default)]
+    posts: Vec<PostedBlind>,  // Antes, blinds and straddles, in the order they went in
+    #[serde(default)]
+    rake: u32,  // What the house took out of the pot at showdown
+}
+
+/// The kinds of forced bet posted before a hand is played.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum BlindKind {
+    Ante,
+    SmallBlind,
+    BigBlind,
+    Straddle,
+}
+
+/// A forced bet as stored in the hand history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostedBlind {
+    seat: usize,
+    kind: BlindKind,
+    amount: u32,  // What actually went in, less than the blind for a short stack
+}
+
+/// The board and pot at the start of one betting round.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreetSnapshot {
+    phase: GamePhase,
+    community_cards: Vec<Card>,
+    pot: u32,
+}
+
+/// How one player still in the hand fared at showdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShowdownResult {
+    player_index: usize,
+    hand_type: HandType,
+    description: String,
+    amount_won: u32,
+    all_in: bool,
+    #[serde(default)]
+    best_cards: Vec<Card>,  // The five cards making the hand, for highlighting
 }
 
+/// An action as stored in the hand history, tagged with the street it was taken on.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAction {
+    #[serde(flatten)]
+    action: Action,
+    phase: GamePhase,
+    #[serde(default)]
+    pot: u32,  // The pot once the action was taken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tell: Option<String>,  // What a robot gave away while acting
+    #[serde(default)]
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    elapsed_ms: u64,  // Time since the previous action, or since the deal for the first one
+}
+
+/// A recorded hand as it stood partway through, for scrubbing through a hand review.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaySnapshot {
+    hand_index: usize,
+    action_index: usize,
+    phase: GamePhase,
+    community_cards: Vec<Card>,
+    pot: u32,
+    actions: Vec<RecordedAction>,  // Everything done so far, in order
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum GamePhase {
     PreFlop,
     Flop,
     Turn,
     River,
+    AfterDraw,  // The betting round after everyone has drawn in five-card draw
     Showdown,
 }
 
+impl GamePhase {
+    /// The betting street corresponding to the number of community cards dealt.
+    fn for_board(community_cards: usize) -> Self {
+        match community_cards {
+            0..=2 => GamePhase::PreFlop,
+            3 => GamePhase::Flop,
+            4 => GamePhase::Turn,
+            _ => GamePhase::River,
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders per-player session statistics as CSV, one row per seat after a header row.
+fn stats_to_csv(stats: &GameStats) -> String {
+    let mut out = String::from("player,games_played,games_won,total_profit,biggest_pot,best_hand,favorite_action\n");
+    for (i, player) in stats.players.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            i,
+            player.games_played,
+            player.games_won,
+            player.total_profit,
+            player.biggest_pot,
+            csv_field(&player.best_hand),
+            csv_field(&player.favorite_action),
+        );
+    }
+    out
+}
+
+fn format_cards(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// The game as PokerStars names it in a hand history header, e.g. "Hold'em No Limit".
+fn pokerstars_game_name(variant: Variant, betting_structure: BettingStructure) -> String {
+    let game = match variant {
+        Variant::Holdem => "Hold'em",
+        Variant::Omaha => "Omaha",
+        Variant::ShortDeck => "6+ Hold'em",
+        Variant::FiveCardDraw => "5 Card Draw",
+    };
+    let limit = match betting_structure {
+        BettingStructure::NoLimit => "No Limit",
+        BettingStructure::PotLimit => "Pot Limit",
+        BettingStructure::FixedLimit => "Limit",
+    };
+    format!("{} {}", game, limit)
+}
+
+/// Renders a recorded hand in the PokerStars hand-history text format understood by most trackers.
+fn to_pokerstars_format(
+    history: &HandHistory,
+    hand_number: usize,
+    players: &[Player],
+    variant: Variant,
+    betting_structure: BettingStructure,
+) -> String {
+    let mut out = String::new();
+    let name = |i: usize| players.get(i).map_or_else(|| format!("Seat {}", i + 1), |p| p.name.clone());
+
+    let _ = writeln!(
+        out,
+        "PokerStars Hand #{}: {} - {}",
+        hand_number,
+        pokerstars_game_name(variant, betting_structure),
+        history.timestamp.format("%Y/%m/%d %H:%M:%S UTC")
+    );
+    let _ = writeln!(out, "Table 'PokerBot' {}-max Seat #{} is the button", players.len(), history.dealer + 1);
+    for (i, player) in players.iter().enumerate() {
+        // Histories recorded before starting stacks were kept fall back to the current stack.
+        let chips = history.starting_stacks.get(i).copied().unwrap_or(player.chips);
+        let _ = writeln!(out, "Seat {}: {} ({} in chips)", i + 1, player.name, chips);
+    }
+    for post in &history.posts {
+        let kind = match post.kind {
+            BlindKind::Ante => "the ante",
+            BlindKind::SmallBlind => "small blind",
+            BlindKind::BigBlind => "big blind",
+            BlindKind::Straddle => "straddle",
+        };
+        let _ = writeln!(out, "{}: posts {} {}", name(post.seat), kind, post.amount);
+    }
+
+    let _ = writeln!(out, "*** HOLE CARDS ***");
+    for (i, cards) in history.player_cards.iter().enumerate() {
+        if !cards.is_empty() && !players.get(i).is_some_and(|p| p.is_robot) {
+            let _ = writeln!(out, "Dealt to {} [{}]", name(i), format_cards(cards));
+        }
+    }
+
+    // Street bets reset at every new street, mirroring how the table tracks them. Preflop they
+    // start from the blinds; antes are dead money and don't count.
+    let mut street = GamePhase::PreFlop;
+    let mut player_bets = vec![0u32; players.len().max(history.player_cards.len())];
+    for post in history.posts.iter().filter(|post| post.kind != BlindKind::Ante) {
+        if let Some(bet) = player_bets.get_mut(post.seat) {
+            *bet += post.amount;
+        }
+    }
+    let mut street_bet = player_bets.iter().copied().max().unwrap_or(0);
+    for recorded in &history.actions {
+        if recorded.phase != street {
+            street = recorded.phase.clone();
+            street_bet = 0;
+            player_bets.iter_mut().for_each(|b| *b = 0);
+            let header = match street {
+                GamePhase::Flop => Some(("FLOP", 3)),
+                GamePhase::Turn => Some(("TURN", 4)),
+                GamePhase::River => Some(("RIVER", 5)),
+                _ => None,
+            };
+            if let Some((label, board_len)) = header {
+                let board = &history.community_cards[..board_len.min(history.community_cards.len())];
+                let _ = writeln!(out, "*** {} *** [{}]", label, format_cards(board));
+            } else if street == GamePhase::AfterDraw {
+                let _ = writeln!(out, "*** FIRST DRAW ***");
+            }
+        }
+
+        let action = &recorded.action;
+        let who = name(action.player_index);
+        let bet = player_bets.get_mut(action.player_index);
+        let line = match (&action.action_type, bet) {
+            (ActionType::Fold, _) => format!("{}: folds", who),
+            (ActionType::Check, _) => format!("{}: checks", who),
+            (ActionType::Call, Some(bet)) => {
+                let amount = street_bet.saturating_sub(*bet);
+                *bet = street_bet;
+                format!("{}: calls {}", who, amount)
+            },
+            (ActionType::Bet, Some(bet)) => {
+                let amount = action.amount.unwrap_or(0);
+                *bet = amount;
+                street_bet = amount;
+                format!("{}: bets {}", who, amount)
+            },
+            (ActionType::Raise, Some(bet)) => {
+                let amount = action.amount.unwrap_or(0);
+                let line = format!("{}: raises {} to {}", who, amount.saturating_sub(street_bet), amount);
+                *bet = amount;
+                street_bet = amount;
+                line
+            },
+            (ActionType::Show, _) => {
+                let cards = history.player_cards.get(action.player_index).cloned().unwrap_or_default();
+                format!("{}: shows [{}]", who, format_cards(&cards))
+            },
+            (ActionType::Muck, _) => format!("{}: mucks hand", who),
+            (action_type, None) => format!("{}: {:?}", who, action_type),
+        };
+        let _ = writeln!(out, "{}", line);
+    }
+
+    // A split pot is collected by each of its winners; a hand won without a showdown has none.
+    let winnings: Vec<(usize, u32)> = if history.showdown_results.is_empty() {
+        history.winner.map(|winner| (winner, history.pot_size)).into_iter().collect()
+    } else {
+        history.showdown_results.iter().filter(|r| r.amount_won > 0).map(|r| (r.player_index, r.amount_won)).collect()
+    };
+    for &(seat, amount) in &winnings {
+        let _ = writeln!(out, "{} collected {} from pot", name(seat), amount);
+    }
+
+    let _ = writeln!(out, "*** SUMMARY ***");
+    let _ = writeln!(out, "Total pot {} | Rake {}", history.pot_size, history.rake);
+    if !history.community_cards.is_empty() {
+        let _ = writeln!(out, "Board [{}]", format_cards(&history.community_cards));
+    }
+    for (i, cards) in history.player_cards.iter().enumerate() {
+        let won = winnings.iter().find(|&&(seat, _)| seat == i);
+        let result = if let Some((_, amount)) = won {
+            format!("won ({})", amount)
+        } else if cards.is_empty() {
+            "folded".to_string()
+        } else {
+            "lost".to_string()
+        };
+        let _ = writeln!(out, "Seat {}: {} {}", i + 1, name(i), result);
+    }
+    out
+}
+
 // Global game state wrapped in a Mutex for thread safety.
 static GAME_STATE: Lazy<TokioMutex<Option<GameState>>> = Lazy::new(|| TokioMutex::new(None));
 
+// Every state change is broadcast here for the spectator stream.
+static STATE_UPDATES: Lazy<broadcast::Sender<GameState>> = Lazy::new(|| broadcast::channel(16).0);
+
+/// Moves the state to a new version after a change and broadcasts it. Having nobody subscribed
+/// is not an error.
+fn publish_state(game: &mut GameState) {
+    game.bump_version();
+    let _ = STATE_UPDATES.send(game.clone());
+}
+
 /// Endpoint to create a new game.
-async fn new_game(Query(query): Query<NewGameQuery>) -> Json<GameState> {
+async fn new_game(
+    Query(query): Query<NewGameQuery>,
+) -> Result<Json<GameState>, (StatusCode, Json<serde_json::Value>)> {
+    create_game(query).await
+}
+
+/// Endpoint to create a new game from a JSON body, for settings awkward to URL-encode.
+async fn new_game_json(
+    JsonExtractor(query): JsonExtractor<NewGameQuery>,
+) -> Result<Json<GameState>, (StatusCode, Json<serde_json::Value>)> {
+    create_game(query).await
+}
+
+/// Starts the game both `/new-game` variants describe and makes it the active one.
+async fn create_game(query: NewGameQuery) -> Result<Json<GameState>, (StatusCode, Json<serde_json::Value>)> {
     println!("Creating new game with {} players in {:?} mode", query.num_players, query.game_mode);
-    let game = GameState::new(query.num_players, query.game_mode, query.starting_chips);
+    activate_game(GameState::new(&query)).await
+}
+
+/// Debug endpoint starting a game whose first hand is dealt from the given deck, for tutorials
+/// and demos that need a particular deal.
+async fn new_game_rigged(
+    JsonExtractor(request): JsonExtractor<RiggedGameRequest>,
+) -> Result<Json<GameState>, (StatusCode, Json<serde_json::Value>)> {
+    if !debug_endpoints_enabled() {
+        return Err((StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Debug endpoints are disabled" }))));
+    }
+    println!("Creating rigged game with {} players", request.config.num_players);
+    let deck = request.deck.iter().map(|t| t.parse::<Card>()).collect::<Result<Vec<_>, _>>();
+    activate_game(deck.and_then(|deck| GameState::new_with_deck(&request.config, deck))).await
+}
+
+/// Makes a freshly created game the active one, or reports why it couldn't be created.
+async fn activate_game(game: Result<GameState, String>) -> Result<Json<GameState>, (StatusCode, Json<serde_json::Value>)> {
+    let mut game = game.map_err(|e| {
+        println!("Rejected new game: {}", e);
+        (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e })))
+    })?;
     {
         let mut state = GAME_STATE.lock().await;
+        // Versions keep counting up across games, so a client's old version is never mistaken
+        // for one of the new game's.
+        game.version = state.as_ref().map_or(0, |old| old.version);
+        publish_state(&mut game);
         *state = Some(game.clone());
+        if game.robot_to_act() {
+            spawn_robot_turns();
+        }
     }
     println!("Game created successfully");
-    Json(game)
+    Ok(Json(game.human_view()))
+}
+
+/// Set while a `play_robot_turns` loop is playing the robots, so a second one never acts
+/// alongside it and cuts a robot's think time short.
+static ROBOT_TURNS_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Marks the robot loop as running until dropped, even if the loop is cancelled mid-sleep.
+struct RobotTurnsRunning;
+
+impl Drop for RobotTurnsRunning {
+    fn drop(&mut self) {
+        ROBOT_TURNS_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Starts `play_robot_turns` unless a loop is already running. Called with the game lock held.
+fn spawn_robot_turns() {
+    if !ROBOT_TURNS_RUNNING.swap(true, Ordering::SeqCst) {
+        tokio::spawn(play_robot_turns(RobotTurnsRunning));
+    }
+}
+
+/// Plays out the robots' turns in a game with a think delay, one action per delay, publishing
+/// each as it happens. The game lock is released while a robot thinks so other requests are
+/// served in the meantime. The loop stops with the lock still held, so a request that hands
+/// the turn back to a robot afterwards always finds it stopped and starts another.
+async fn play_robot_turns(running: RobotTurnsRunning) {
+    loop {
+        let delay = match *GAME_STATE.lock().await {
+            Some(ref game) if game.robot_to_act() => game.robot_delay_ms.unwrap_or(0),
+            _ => {
+                drop(running);
+                return;
+            },
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+
+        let mut state = GAME_STATE.lock().await;
+        let Some(ref mut game) = *state else {
+            drop(running);
+            return;
+        };
+        // The table may have moved on while the robot was thinking.
+        if !game.robot_to_act() {
+            drop(running);
+            return;
+        }
+        if let Err(e) = game.handle_robot_action() {
+            println!("Error handling robot action: {}", e);
+            drop(running);
+            return;
+        }
+        publish_state(game);
+    }
 }
 
 /// Endpoint to handle player actions
 async fn player_action(
     JsonExtractor(action): JsonExtractor<PlayerAction>,
-) -> Json<Result<GameState, String>> {
+) -> Json<Result<GameState, GameError>> {
     println!("Received player action: {:?}", action);
     let mut state = GAME_STATE.lock().await;
     if let Some(ref mut game) = *state {
         let action = Action {
-            player_index: 0, // Human player is always index 0
+            player_index: action.player_index,
             action_type: action.action_type,
             amount: action.amount,
         };
-        
-        match game.handle_action(action) {
+        if game.players.get(action.player_index).is_none_or(|p| p.is_robot) {
+            println!("Rejected action for seat {}: not a human seat", action.player_index);
+            return Json(Err(GameError::NotHumanSeat));
+        }
+        let seat = action.player_index;
+        match game.handle_action(action).and_then(|_| game.advance_until_human()) {
             Ok(_) => {
                 println!("Action handled successfully");
-                Json(Ok(game.clone()))
+                publish_state(game);
+                if game.robot_to_act() {
+                    spawn_robot_turns();
+                }
+                Json(Ok(game.human_view_for(seat)))
             },
             Err(e) => {
                 println!("Error handling action: {}", e);
@@ -796,76 +4273,3881 @@ async fn player_action(
         }
     } else {
         println!("No active game found");
-        Json(Err("No active game".to_string()))
+        Json(Err(GameError::NoActiveGame))
     }
 }
 
-/// Endpoint to deal the flop.
-async fn deal_flop() -> Json<GameState> {
-    let mut state = GAME_STATE.lock().await;
-    if let Some(ref mut game) = *state {
-        println!("Dealing flop");
-        game.deal_flop();
-        println!("Community cards: {:?}", game.community_cards);
-        for (i, player) in game.players.iter().enumerate() {
-            println!("Player {} win probability: {:.1}%", 
-                i + 1, player.win_probability * 100.0);
+/// Endpoint to fetch the hand history of the active game. Filtered by phase, each hand keeps only
+/// the actions taken on that street, and hands with none are left out.
+async fn hand_history(Query(query): Query<HandHistoryQuery>) -> Json<Result<Vec<HandHistory>, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        let mut history = game.history_view();
+        if let Some(phase) = query.phase {
+            for hand in &mut history {
+                hand.actions.retain(|a| a.phase == phase);
+            }
+            history.retain(|hand| !hand.actions.is_empty());
         }
-        return Json(game.clone());
+        Json(Ok(history))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
     }
-    Json(GameState::new(2, GameMode::Simulation, 1000))
 }
 
-/// Endpoint to deal the turn.
+/// Endpoint replaying a recorded hand up to a given action.
+async fn replay(Query(query): Query<ReplayQuery>) -> Json<Result<ReplaySnapshot, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(game.replay(query.hand_index, query.action_index))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint to export a recorded hand as PokerStars-format text.
+async fn export_hand(Query(query): Query<ExportHandQuery>) -> Result<String, (StatusCode, String)> {
+    let state = GAME_STATE.lock().await;
+    let game = state
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "No active game".to_string()))?;
+    let history = game
+        .history_view()
+        .into_iter()
+        .nth(query.index)
+        .ok_or((StatusCode::NOT_FOUND, format!("No hand at index {}", query.index)))?;
+    Ok(to_pokerstars_format(&history, query.index + 1, &game.players, game.variant, game.betting_structure))
+}
+
+/// Endpoint returning one player's session statistics.
+async fn player_stats(Path(index): Path<usize>) -> Result<Json<PlayerStats>, (StatusCode, Json<GameError>)> {
+    let state = GAME_STATE.lock().await;
+    let game = state.as_ref().ok_or((StatusCode::NOT_FOUND, Json(GameError::NoActiveGame)))?;
+    let stats = game.stats.players.get(index).ok_or((StatusCode::NOT_FOUND, Json(GameError::NoSuchSeat)))?;
+    Ok(Json(stats.clone()))
+}
+
+/// Endpoint to download the session statistics as CSV.
+async fn stats_csv() -> Result<impl IntoResponse, (StatusCode, String)> {
+    let state = GAME_STATE.lock().await;
+    let game = state
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "No active game".to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "text/csv")], stats_to_csv(&game.stats)))
+}
+
+/// Endpoint to start the next hand in the active game.
+async fn new_hand() -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        match game.deal_new_hand() {
+            Ok(()) => {
+                println!("Dealt hand #{}", game.hand_history.len());
+                publish_state(game);
+                if game.robot_to_act() {
+                    spawn_robot_turns();
+                }
+                Json(Ok(game.human_view()))
+            },
+            Err(e) => {
+                println!("Error dealing new hand: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint letting a busted human buy back in before the next hand.
+async fn rebuy(Query(query): Query<RebuyQuery>) -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        let seat = query.player_index;
+        if game.players.get(seat).is_none_or(|p| p.is_robot) {
+            return Json(Err(GameError::NotHumanSeat));
+        }
+        match game.rebuy(seat) {
+            Ok(()) => {
+                println!("Seat {} rebought for {}", seat, game.rebuy_amount);
+                publish_state(game);
+                Json(Ok(game.human_view_for(seat)))
+            },
+            Err(e) => {
+                println!("Rejected rebuy: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Debugging endpoint playing exactly one robot decision when it's a robot's turn.
+async fn step_robot() -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        match game.step_robot() {
+            Ok(action) => {
+                println!("Stepped robot action: {:?}", action);
+                publish_state(game);
+                Json(Ok(game.human_view()))
+            },
+            Err(e) => {
+                println!("Error stepping robot: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint showing each all-in player's chances of winning or tying over the rest of the board.
+async fn all_in_equity() -> Json<Result<Vec<SeatEquity>, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(game.all_in_equity())
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint showing each player's chances on a hypothetical board, without changing the game.
+async fn what_if(JsonExtractor(request): JsonExtractor<WhatIfRequest>) -> Json<Result<Vec<SeatEquity>, GameError>> {
+    let board = match request.board.iter().map(|t| t.parse::<Card>()).collect::<Result<Vec<_>, _>>() {
+        Ok(board) => board,
+        Err(e) => return Json(Err(e.into())),
+    };
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(game.what_if(&board))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint describing the seats, the button and blinds, and whose turn it is.
+async fn table_view() -> Json<Result<TableView, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(Ok(game.table_view()))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint returning only what has changed since a version the client already has.
+async fn state_diff(Query(query): Query<DiffQuery>) -> Json<Result<StateDiff, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(Ok(game.diff_since(query.since)))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint listing what the player to act may do, so clients don't re-implement the rules.
+async fn legal_actions() -> Json<Result<LegalActions, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(game.legal_actions(game.current_player))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint estimating equity for an arbitrary spot, independent of the live game.
+async fn simulate(
+    JsonExtractor(request): JsonExtractor<SimulateRequest>,
+) -> Json<Result<SimulateResponse, GameError>> {
+    let response = simulate_spot(&request).map(|(win_probability, completed)| {
+        let standard_error = standard_error(win_probability, completed);
+        let confidence_interval = confidence_interval_95(win_probability, standard_error);
+        SimulateResponse { win_probability, standard_error, confidence_interval }
+    });
+    Json(response.map_err(GameError::from))
+}
+
+/// Endpoint working out how often a bluff must get a fold to break even.
+async fn fold_equity(
+    JsonExtractor(request): JsonExtractor<FoldEquityRequest>,
+) -> Json<Result<FoldEquityResponse, GameError>> {
+    Json(fold_equity_spot(&request).map_err(GameError::from))
+}
+
+/// Endpoint listing the cards that would improve a hand on the flop or turn.
+async fn remaining_outs(JsonExtractor(request): JsonExtractor<OutsRequest>) -> Json<Result<OutsResponse, GameError>> {
+    Json(outs_spot(&request).map_err(GameError::from))
+}
+
+/// Endpoint naming the best five-card hand among 5 to 7 cards.
+async fn evaluate(JsonExtractor(request): JsonExtractor<EvaluateRequest>) -> Json<Result<EvaluateResponse, GameError>> {
+    Json(evaluate_spot(&request).map_err(GameError::from))
+}
+
+/// Endpoint listing the hand types in order, for a help screen.
+async fn rankings() -> Json<Vec<HandRanking>> {
+    Json(hand_rankings())
+}
+
+/// Endpoint reporting the crate version and the commit and time it was built from.
+async fn version() -> Json<VersionInfo> {
+    let built_at = env!("POKERBOT_BUILD_TIMESTAMP")
+        .parse()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0));
+    Json(VersionInfo { version: env!("CARGO_PKG_VERSION"), git_hash: env!("POKERBOT_GIT_HASH"), built_at })
+}
+
+/// Endpoint showing the board that would have been dealt after everyone else folded.
+async fn rabbit_hunt() -> Json<Result<Vec<Card>, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(game.rabbit_hunt())
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint re-estimating every player's win probability on the current board without dealing,
+/// optionally with a different number of simulations. Returns the probabilities by seat.
+async fn recompute_probabilities(Query(query): Query<ProbabilitiesQuery>) -> Json<Result<Vec<f64>, GameError>> {
+    let sims = query.sims.unwrap_or(DEFAULT_EQUITY_SIMULATIONS);
+    if !(1..=MAX_SIMULATIONS).contains(&sims) {
+        return Json(Err(format!("Sims must be between 1 and {}", MAX_SIMULATIONS).into()));
+    }
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        game.update_probabilities_with(sims);
+        publish_state(game);
+        Json(Ok(game.human_view().players.iter().map(|p| p.win_probability).collect()))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint turning the human's win-probability display on or off mid-game.
+async fn reveal_probabilities(Query(query): Query<RevealProbabilitiesQuery>) -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        game.show_probabilities = query.show;
+        publish_state(game);
+        Json(Ok(game.human_view()))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint fast-forwarding a Simulation hand to showdown.
+async fn auto_play() -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        match game.auto_play() {
+            Ok(winners) => {
+                println!("Auto-played hand won by {:?}", winners);
+                publish_state(game);
+                Json(Ok(game.human_view()))
+            },
+            Err(e) => {
+                println!("Error auto-playing hand: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint ranking the players by profit over the session.
+async fn leaderboard() -> Json<Result<Vec<LeaderboardEntry>, GameError>> {
+    let state = GAME_STATE.lock().await;
+    if let Some(ref game) = *state {
+        Json(Ok(game.leaderboard()))
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint that applies the auto-action to a player whose action deadline has passed.
+async fn tick() -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        match game.enforce_action_deadline(chrono::Utc::now()) {
+            Ok(action) => {
+                if let Some(action) = action {
+                    println!("Player {} timed out, auto-{:?}", action.player_index, action.action_type);
+                    publish_state(game);
+                }
+                Json(Ok(game.human_view()))
+            },
+            Err(e) => {
+                println!("Error applying timeout action: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint streaming the game to read-only spectators as server-sent events, one event per
+/// state change. Pass `reveal=true` to include hole cards before the showdown.
+async fn spectate(Query(query): Query<SpectateQuery>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let updates = BroadcastStream::new(STATE_UPDATES.subscribe()).filter_map(move |update| {
+        // A subscriber that falls behind skips the states it missed.
+        let game = update.ok()?;
+        Event::default().json_data(game.spectator_view(query.reveal)).ok().map(Ok)
+    });
+    Sse::new(updates).keep_alive(KeepAlive::default())
+}
+
+/// Debug-only endpoints are served when `POKERBOT_DEBUG` is `1` or `true`.
+fn debug_endpoints_enabled() -> bool {
+    std::env::var("POKERBOT_DEBUG").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Debug endpoint returning the remaining deck of the active game, top card last.
+async fn get_deck() -> Result<Json<Vec<Card>>, (StatusCode, String)> {
+    if !debug_endpoints_enabled() {
+        return Err((StatusCode::NOT_FOUND, "Debug endpoints are disabled".to_string()));
+    }
+    let state = GAME_STATE.lock().await;
+    let game = state
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "No active game".to_string()))?;
+    Ok(Json(game.deck.clone()))
+}
+
+/// Result of `/deck/verify`: whether every card is accounted for, and what's wrong if not.
+#[derive(Debug, Serialize)]
+pub struct DeckIntegrity {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    discrepancy: Option<String>,
+}
+
+/// Debug endpoint checking that the active game's cards are exactly one full deck.
+async fn verify_deck() -> Result<Json<DeckIntegrity>, (StatusCode, String)> {
+    if !debug_endpoints_enabled() {
+        return Err((StatusCode::NOT_FOUND, "Debug endpoints are disabled".to_string()));
+    }
+    let state = GAME_STATE.lock().await;
+    let game = state
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "No active game".to_string()))?;
+    let discrepancy = game.check_deck_integrity().err();
+    Ok(Json(DeckIntegrity { ok: discrepancy.is_none(), discrepancy }))
+}
+
+/// Debug endpoint timing the evaluator and simulator over random deals, for tuning.
+async fn benchmark(Query(query): Query<BenchmarkQuery>) -> Result<Json<BenchmarkReport>, (StatusCode, String)> {
+    if !debug_endpoints_enabled() {
+        return Err((StatusCode::NOT_FOUND, "Debug endpoints are disabled".to_string()));
+    }
+    run_benchmark(query.hands).map(Json).map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// Debug endpoint returning the full, unredacted state of the active game.
+async fn get_state() -> Result<Json<FullGameState>, (StatusCode, String)> {
+    if !debug_endpoints_enabled() {
+        return Err((StatusCode::NOT_FOUND, "Debug endpoints are disabled".to_string()));
+    }
+    let state = GAME_STATE.lock().await;
+    let game = state
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "No active game".to_string()))?;
+    Ok(Json(game.with_deck()))
+}
+
+/// Endpoint to resolve the current hand at showdown.
+async fn showdown(Query(query): Query<ShowdownQuery>) -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        let result = if query.run_it_twice { game.showdown_run_twice() } else { game.showdown() };
+        match result {
+            Ok(winners) => {
+                println!("Showdown won by {:?}", winners);
+                publish_state(game);
+                Json(Ok(game.human_view()))
+            },
+            Err(e) => {
+                println!("Error resolving showdown: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint to deal the flop.
+async fn deal_flop() -> Json<GameState> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        if !game.board_has_room(3) {
+            println!("No flop to deal");
+            return Json(game.human_view());
+        }
+        println!("Dealing flop");
+        game.deal_flop();
+        log_board(game);
+        play_new_street(game);
+        publish_state(game);
+        return Json(game.human_view());
+    }
+    Json(GameState::new(&NewGameQuery::default()).expect("default game settings are valid"))
+}
+
+/// Endpoint for a five-card draw player to discard and draw replacements.
+async fn draw(JsonExtractor(request): JsonExtractor<DrawRequest>) -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        match game.draw(request.player_index, &request.discard) {
+            Ok(replacements) => {
+                println!("Player {} drew {} cards", request.player_index, replacements.len());
+                play_new_street(game);
+                publish_state(game);
+                Json(Ok(game.human_view_for(request.player_index)))
+            },
+            Err(e) => {
+                println!("Error drawing: {}", e);
+                Json(Err(e))
+            },
+        }
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
+    }
+}
+
+/// Endpoint to deal the turn.
 async fn deal_turn() -> Json<GameState> {
     let mut state = GAME_STATE.lock().await;
     if let Some(ref mut game) = *state {
+        if !game.board_has_room(1) {
+            println!("No turn to deal");
+            return Json(game.human_view());
+        }
         println!("Dealing turn");
         game.deal_turn();
-        println!("Community cards: {:?}", game.community_cards);
-        for (i, player) in game.players.iter().enumerate() {
-            println!("Player {} win probability: {:.1}%", 
-                i + 1, player.win_probability * 100.0);
-        }
-        return Json(game.clone());
+        log_board(game);
+        play_new_street(game);
+        publish_state(game);
+        return Json(game.human_view());
     }
-    Json(GameState::new(2, GameMode::Simulation, 1000))
+    Json(GameState::new(&NewGameQuery::default()).expect("default game settings are valid"))
 }
 
 /// Endpoint to deal the river.
 async fn deal_river() -> Json<GameState> {
     let mut state = GAME_STATE.lock().await;
     if let Some(ref mut game) = *state {
+        if !game.board_has_room(1) {
+            println!("No river to deal");
+            return Json(game.human_view());
+        }
         println!("Dealing river");
         game.deal_river();
-        println!("Community cards: {:?}", game.community_cards);
-        for (i, player) in game.players.iter().enumerate() {
-            println!("Player {} win probability: {:.1}%", 
-                i + 1, player.win_probability * 100.0);
+        log_board(game);
+        play_new_street(game);
+        publish_state(game);
+        return Json(game.human_view());
+    }
+    Json(GameState::new(&NewGameQuery::default()).expect("default game settings are valid"))
+}
+
+/// Endpoint dealing whichever street comes next.
+async fn deal_next_street() -> Json<Result<GameState, GameError>> {
+    let mut state = GAME_STATE.lock().await;
+    if let Some(ref mut game) = *state {
+        match game.deal_next_street() {
+            Ok(phase) => {
+                println!("Dealt the {:?}", phase);
+                log_board(game);
+                play_new_street(game);
+                publish_state(game);
+                Json(Ok(game.human_view()))
+            },
+            Err(e) => {
+                println!("Error dealing the next street: {}", e);
+                Json(Err(e))
+            },
         }
-        return Json(game.clone());
+    } else {
+        println!("No active game found");
+        Json(Err(GameError::NoActiveGame))
     }
-    Json(GameState::new(2, GameMode::Simulation, 1000))
 }
 
-#[tokio::main]
-async fn main() {
-    println!("Starting poker server...");
+/// Lets the robots act on a freshly dealt street up to the human's turn and restarts the action
+/// clock. Robots with a think delay are played out in the background instead.
+fn play_new_street(game: &mut GameState) {
+    if let Err(e) = game.advance_until_human() {
+        println!("Error handling robot action: {}", e);
+    }
+    game.refresh_action_deadline();
+    if game.robot_to_act() {
+        spawn_robot_turns();
+    }
+}
+
+/// Logs the board and everyone's win probability after a street is dealt.
+fn log_board(game: &GameState) {
+    println!("Community cards: {:?}", game.community_cards);
+    for (i, player) in game.players.iter().enumerate() {
+        println!("Player {} win probability: {:.1}%", i + 1, player.win_probability * 100.0);
+    }
+}
+
+const DEFAULT_ADDR: &str = "0.0.0.0:3000";
+
+/// Parses a bind address such as `127.0.0.1:3000` or `[::1]:8080`.
+fn parse_bind_address(addr: &str) -> Result<SocketAddr, String> {
+    addr.trim()
+        .parse()
+        .map_err(|e| format!("Invalid bind address '{}': {} (expected e.g. 127.0.0.1:3000)", addr, e))
+}
+
+/// Picks the bind address from `--addr <addr>` / `--addr=<addr>`, then `POKERBOT_ADDR`,
+/// falling back to `0.0.0.0:3000`.
+fn bind_address(args: &[String], env_addr: Option<String>) -> Result<SocketAddr, String> {
+    let mut from_args = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some(value) = arg.strip_prefix("--addr=") {
+            from_args = Some(value.to_string());
+        } else if arg == "--addr" {
+            from_args = Some(iter.next().ok_or("--addr requires a value")?.clone());
+        }
+    }
+    let addr = from_args.or(env_addr).unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    parse_bind_address(&addr)
+}
+
+/// Builds the CORS layer from a comma-separated `POKERBOT_CORS_ORIGINS` allow-list, allowing any
+/// origin when it is unset. Listed origins may send credentials; browsers refuse credentials with
+/// wildcard headers, so their request headers are mirrored instead.
+fn cors_layer(env_origins: Option<String>) -> Result<CorsLayer, String> {
+    let cors = CorsLayer::new().allow_methods([Method::GET, Method::POST]);
+    let Some(env_origins) = env_origins else {
+        return Ok(cors.allow_origin(Any).allow_headers(AllowHeaders::any()));
+    };
+    let origins = env_origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            if origin == "*" || !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                return Err(format!("Invalid CORS origin '{}': expected e.g. https://example.com", origin));
+            }
+            HeaderValue::from_str(origin).map_err(|e| format!("Invalid CORS origin '{}': {}", origin, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if origins.is_empty() {
+        return Err("POKERBOT_CORS_ORIGINS is set but lists no origins".to_string());
+    }
+    Ok(cors
+        .allow_origin(origins)
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true))
+}
+
+/// Writes the game, deck included, to `path` as JSON.
+fn save_game(path: &FsPath, game: &GameState) -> Result<(), String> {
+    let json = serde_json::to_string(&game.with_deck()).map_err(|e| format!("Failed to serialize the game: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Reads a game saved by `save_game`, rejecting one whose cards have been tampered with.
+fn load_game(path: &FsPath) -> Result<GameState, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let game: GameState = serde_json::from_str(&json).map_err(|e| format!("Invalid saved game: {}", e))?;
+    game.validate_no_duplicates()?;
+    Ok(game)
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM where there is one.
+async fn termination_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            },
+            Err(e) => {
+                eprintln!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            },
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Waits for `signal`, then saves the active game to `save_path` when one is configured so the
+/// next start can pick it back up.
+async fn shutdown_signal(signal: impl std::future::Future<Output = ()>, save_path: Option<PathBuf>) {
+    signal.await;
+    println!("Shutting down...");
+    let Some(path) = save_path else { return };
+    if let Some(ref game) = *GAME_STATE.lock().await {
+        match save_game(&path, game) {
+            Ok(()) => println!("Saved the active game to {}", path.display()),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+/// Largest request body accepted; anything bigger is rejected before it's buffered.
+const MAX_REQUEST_BODY_BYTES: usize = 64 * 1024;
 
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST])
-        .allow_origin(Any)
-        .allow_headers(AllowHeaders::any());
+/// How long a request may take before it's abandoned with a timeout response.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
-    let app = Router::new()
-        .route("/new-game", get(new_game))
+/// Every route the server answers, behind the body limit, timeout and `cors` layers.
+fn router(cors: CorsLayer) -> Router {
+    Router::new()
+        .route("/new-game", get(new_game).post(new_game_json))
+        .route("/new-game-rigged", post(new_game_rigged))
         .route("/player-action", post(player_action))
         .route("/deal-flop", get(deal_flop))
         .route("/deal-turn", get(deal_turn))
         .route("/deal-river", get(deal_river))
-        .layer(cors);
+        .route("/deal-street", post(deal_next_street))
+        .route("/draw", post(draw))
+        .route("/showdown", post(showdown))
+        .route("/new-hand", post(new_hand))
+        .route("/step-robot", post(step_robot))
+        .route("/rebuy", post(rebuy))
+        .route("/legal-actions", get(legal_actions))
+        .route("/table-view", get(table_view))
+        .route("/diff", get(state_diff))
+        .route("/all-in-equity", get(all_in_equity))
+        .route("/whatif", post(what_if))
+        .route("/tick", post(tick))
+        .route("/hand-history", get(hand_history))
+        .route("/export-hand", get(export_hand))
+        .route("/replay", get(replay))
+        .route("/stats.csv", get(stats_csv))
+        .route("/stats/:player", get(player_stats))
+        .route("/leaderboard", get(leaderboard))
+        .route("/rabbit-hunt", get(rabbit_hunt))
+        .route("/auto-play", post(auto_play))
+        .route("/reveal-probabilities", post(reveal_probabilities))
+        .route("/probabilities", post(recompute_probabilities))
+        .route("/simulate", post(simulate))
+        .route("/evaluate", post(evaluate))
+        .route("/rankings", get(rankings))
+        .route("/version", get(version))
+        .route("/cards/remaining-outs", post(remaining_outs))
+        .route("/fold-equity", post(fold_equity))
+        .route("/spectate", get(spectate))
+        .route("/deck", get(get_deck))
+        .route("/deck/verify", get(verify_deck))
+        .route("/benchmark", get(benchmark))
+        .route("/state", get(get_state))
+        .layer(RequestBodyLimitLayer::new(MAX_REQUEST_BODY_BYTES))
+        .layer(TimeoutLayer::new(REQUEST_TIMEOUT))
+        .layer(cors)
+}
+
+#[tokio::main]
+async fn main() {
+    println!("Starting poker server...");
+
+    let cors = match cors_layer(std::env::var("POKERBOT_CORS_ORIGINS").ok()) {
+        Ok(cors) => cors,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let app = router(cors);
 
-    println!("Server running on http://localhost:3000");
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Enforce action deadlines even when no client is polling /tick.
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            if let Some(ref mut game) = *GAME_STATE.lock().await {
+                match game.enforce_action_deadline(chrono::Utc::now()) {
+                    Ok(Some(action)) => {
+                        println!("Player {} timed out, auto-{:?}", action.player_index, action.action_type);
+                        publish_state(game);
+                    },
+                    Ok(None) => {},
+                    Err(e) => println!("Error applying timeout action: {}", e),
+                }
+            }
+        }
+    });
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let addr = match bind_address(&args, std::env::var("POKERBOT_ADDR").ok()) {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // With POKERBOT_SAVE_FILE set, the game survives a restart.
+    let save_path = std::env::var_os("POKERBOT_SAVE_FILE").map(PathBuf::from);
+    if let Some(path) = save_path.as_ref().filter(|path| path.exists()) {
+        match load_game(path) {
+            Ok(game) => {
+                println!("Restored the game saved in {}", path.display());
+                *GAME_STATE.lock().await = Some(game);
+            },
+            Err(e) => eprintln!("Not restoring the saved game: {}", e),
+        }
+    }
+
+    println!("Server running on http://{}", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(termination_signal(), save_path))
+        .await
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { suit, rank }
+    }
+
+    /// Parses space-separated short notation such as `"Ah Kd Tc"`.
+    fn cards(notation: &str) -> Vec<Card> {
+        notation
+            .split_whitespace()
+            .map(|text| text.parse().unwrap())
+            .collect()
+    }
+
+    /// A simulation-mode game with default chips and blinds.
+    fn test_game(num_players: usize) -> GameState {
+        GameState::new(&NewGameQuery { num_players, ..Default::default() }).unwrap()
+    }
+
+    /// Settings for a `test_game` with a human in every seat, to build on with struct update
+    /// syntax when a test needs anything else changed.
+    fn humans_only(num_players: usize) -> NewGameQuery {
+        NewGameQuery { num_players, human_count: num_players, ..Default::default() }
+    }
+
+    /// The full deck minus the given cards.
+    fn deck_without(used: &[Card]) -> Vec<Card> {
+        standard_deck().into_iter().filter(|c| !used.contains(c)).collect()
+    }
+
+    /// A freshly shuffled deck from the thread RNG.
+    fn new_shuffled_deck() -> Vec<Card> {
+        shuffled_deck(Variant::Holdem, &mut rand::thread_rng())
+    }
+
+    #[test]
+    fn hidden_equity_for_aces_is_near_heads_up_value() {
+        let aces = vec![card(Rank::Ace, Suit::Spades), card(Rank::Ace, Suit::Hearts)];
+        let unseen = deck_without(&aces);
+        // Pocket aces win roughly 85% against a random hand heads-up.
+        let equity = simulate_win_probability_hidden(&aces, 1, &[], &unseen, &[], 4000, Variant::Holdem);
+        assert!((0.81..=0.89).contains(&equity), "AA vs random: {}", equity);
+    }
+
+    /// The cards a player cannot see: the deck plus the opponent's hole cards.
+    fn unseen_for(opponent: &[Card], deck: &[Card]) -> Vec<Card> {
+        let mut unseen = deck.to_vec();
+        unseen.extend(opponent.iter().cloned());
+        unseen
+    }
+
+    #[test]
+    fn exact_and_hidden_modes_differ_against_a_strong_opponent() {
+        let aces = vec![card(Rank::Ace, Suit::Spades), card(Rank::Ace, Suit::Hearts)];
+        let kings = vec![card(Rank::King, Suit::Spades), card(Rank::King, Suit::Hearts)];
+        let deck = deck_without(&[aces.clone(), kings.clone()].concat());
+
+        // Against known kings the aces are about 82%; against a random hand about 85%.
+        let exact = simulate_win_probability(&aces, std::slice::from_ref(&kings), None, &[], &deck, &[], 4000, Variant::Holdem);
+        assert!((0.77..=0.87).contains(&exact), "AA vs KK: {}", exact);
+        let hidden = simulate_win_probability_hidden(&aces, 1, &[], &unseen_for(&kings, &deck), &[], 4000, Variant::Holdem);
+        assert!((0.81..=0.89).contains(&hidden), "AA vs random: {}", hidden);
+
+        // The kings are a big underdog to the known aces but a favourite against a random hand,
+        // so the hidden mode must not be peeking at the opponent's cards.
+        let kings_exact = simulate_win_probability(&kings, std::slice::from_ref(&aces), None, &[], &deck, &[], 4000, Variant::Holdem);
+        let kings_hidden = simulate_win_probability_hidden(&kings, 1, &[], &unseen_for(&aces, &deck), &[], 4000, Variant::Holdem);
+        assert!(kings_exact < 0.25, "KK vs AA: {}", kings_exact);
+        assert!(kings_hidden > 0.75, "KK vs random: {}", kings_hidden);
+    }
+
+    #[test]
+    fn exact_equity_on_the_turn_counts_outs() {
+        let hero = vec![card(Rank::Ace, Suit::Hearts), card(Rank::King, Suit::Hearts)];
+        let villain = vec![card(Rank::Queen, Suit::Spades), card(Rank::Queen, Suit::Diamonds)];
+        let board = vec![
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Seven, Suit::Hearts),
+            card(Rank::Nine, Suit::Clubs),
+            card(Rank::Jack, Suit::Spades),
+        ];
+        let deck = deck_without(&[hero.clone(), villain.clone(), board.clone()].concat());
+        assert_eq!(deck.len(), 44);
+
+        // Nine hearts plus three aces and three kings win; every other river loses.
+        let equity = exact_equity(&hero, std::slice::from_ref(&villain), &board, &deck, Variant::Holdem);
+        assert!((equity - 15.0 / 44.0).abs() < 1e-12, "equity was {}", equity);
+        assert_eq!(equity, exact_equity(&hero, std::slice::from_ref(&villain), &board, &deck, Variant::Holdem));
+    }
+
+    #[test]
+    fn all_in_equity_on_the_turn_covers_every_river() {
+        let mut game = test_game(2);
+        let hero = vec![card(Rank::Ace, Suit::Hearts), card(Rank::King, Suit::Hearts)];
+        let villain = vec![card(Rank::Queen, Suit::Spades), card(Rank::Queen, Suit::Diamonds)];
+        let board = vec![
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Seven, Suit::Hearts),
+            card(Rank::Nine, Suit::Clubs),
+            card(Rank::Jack, Suit::Spades),
+        ];
+        rig_hand(&mut game, &[hero.clone(), villain.clone()], &board);
+        game.deck = deck_without(&[hero.clone(), villain.clone(), board.clone()].concat());
+        assert!(game.all_in_equity().is_err(), "nobody is all-in yet");
+
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(1000) }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        let equity = game.all_in_equity().unwrap();
+        assert_eq!(equity.iter().map(|e| e.seat).collect::<Vec<_>>(), vec![0, 1]);
+        assert!((equity[0].equity.win - 15.0 / 44.0).abs() < 1e-12);
+        let total: f64 = equity.iter().map(|e| e.equity.pot_share()).sum();
+        assert!((total - 1.0).abs() < 1e-9, "equities summed to {}", total);
+        assert_eq!(equity[0].equity.win, equity[1].equity.lose);
+    }
+
+    #[tokio::test]
+    async fn what_if_a_forced_board_completes_the_flush() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = test_game(2);
+        let hero = cards("Ah Kh");
+        let villain = cards("Qs Qd");
+        let board = cards("2h 7h 9c");
+        rig_hand(&mut game, &[hero.clone(), villain.clone()], &board);
+        game.deck = deck_without(&[hero, villain, board.clone()].concat());
+        *GAME_STATE.lock().await = Some(game.clone());
+
+        let forced = |board: &str| WhatIfRequest { board: board.split_whitespace().map(String::from).collect() };
+        let equity = what_if(JsonExtractor(forced("2h 7h 9c 3h"))).await.0.unwrap();
+        assert_eq!(equity[0].equity.win, 1.0, "every river leaves the hero with the best flush");
+        assert_eq!(equity[1].equity.lose, 1.0);
+
+        let err = what_if(JsonExtractor(forced("2h 7h Qs 3h"))).await.0.unwrap_err();
+        assert_eq!(err, GameError::Invalid("Duplicate card Qs".to_string()));
+        let err = what_if(JsonExtractor(forced(""))).await.0.unwrap_err();
+        assert_eq!(err, GameError::Invalid("The board must have 3, 4 or 5 cards".to_string()));
+        let live = GAME_STATE.lock().await.clone().unwrap();
+        assert_eq!(live.community_cards, board);
+        assert_eq!(live.deck, game.deck);
+    }
+
+    #[tokio::test]
+    async fn what_if_keeps_the_robots_equity_to_themselves() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = GameState::new(&NewGameQuery { num_players: 3, game_mode: GameMode::RobotPlay, ..Default::default() }).unwrap();
+        let hands = [cards("Ah Kh"), cards("Qs Qd"), cards("Jc Tc")];
+        let board = cards("2h 7h 9c");
+        rig_hand(&mut game, &hands, &board);
+        game.deck = deck_without(&[hands.concat(), board.clone()].concat());
+        *GAME_STATE.lock().await = Some(game);
+
+        let request = WhatIfRequest { board: vec!["2h".into(), "7h".into(), "9c".into(), "3h".into()] };
+        let equity = what_if(JsonExtractor(request)).await.0.unwrap();
+        assert_eq!(equity.iter().map(|e| e.seat).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(equity[0].equity.win, 1.0);
+    }
+
+    #[test]
+    fn exact_equity_on_the_river_is_the_showdown_result() {
+        let hero = vec![card(Rank::Ace, Suit::Hearts), card(Rank::King, Suit::Hearts)];
+        let villain = vec![card(Rank::Queen, Suit::Spades), card(Rank::Queen, Suit::Diamonds)];
+        let chopper = vec![card(Rank::Ace, Suit::Clubs), card(Rank::King, Suit::Clubs)];
+        let board = vec![
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Seven, Suit::Diamonds),
+            card(Rank::Nine, Suit::Clubs),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Ace, Suit::Spades),
+        ];
+        let deck = deck_without(&[hero.clone(), villain.clone(), chopper.clone(), board.clone()].concat());
+
+        assert_eq!(exact_equity(&hero, std::slice::from_ref(&villain), &board, &deck, Variant::Holdem), 1.0);
+        assert_eq!(exact_equity(&villain, std::slice::from_ref(&hero), &board, &deck, Variant::Holdem), 0.0);
+        assert_eq!(exact_equity(&hero, &[villain.clone(), chopper.clone()], &board, &deck, Variant::Holdem), 0.5);
+    }
+
+    /// Serializes tests that install a game into the global `GAME_STATE`.
+    static GLOBAL_GAME_LOCK: Lazy<TokioMutex<()>> = Lazy::new(|| TokioMutex::new(()));
+
+    #[tokio::test]
+    async fn hand_history_hides_robot_cards_while_the_hand_is_live() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = GameState::new(&NewGameQuery { game_mode: GameMode::RobotPlay, ..humans_only(3) }).unwrap();
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        game.players[1].is_robot = true;
+        game.players[2].is_robot = true;
+        let own_cards = game.players[0].cards.clone();
+        *GAME_STATE.lock().await = Some(game);
+
+        let Json(history) = hand_history(Query(HandHistoryQuery { phase: None })).await;
+        let hand = history.unwrap().pop().unwrap();
+        assert_eq!(hand.actions.len(), 1);
+        assert_eq!(hand.player_cards[0], own_cards);
+        assert!(hand.player_cards[1].is_empty() && hand.player_cards[2].is_empty());
+    }
+
+    #[tokio::test]
+    async fn hand_history_endpoint_returns_recorded_actions_in_order() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = test_game(3);
+        let act = |player_index, action_type, amount| Action { player_index, action_type, amount };
+        let preflop = [act(0, ActionType::Raise, Some(20)), act(1, ActionType::Call, None), act(2, ActionType::Fold, None)];
+        for action in preflop {
+            game.handle_action(action).unwrap();
+        }
+        game.deal_flop();
+        for action in [act(1, ActionType::Check, None), act(0, ActionType::Check, None)] {
+            game.handle_action(action).unwrap();
+        }
+        game.deal_turn();
+        for action in [act(1, ActionType::Check, None), act(0, ActionType::Bet, Some(40)), act(1, ActionType::Call, None)] {
+            game.handle_action(action).unwrap();
+        }
+        game.deal_river();
+        *GAME_STATE.lock().await = Some(game);
+
+        let seats_and_actions = |hand: &HandHistory| -> Vec<_> {
+            hand.actions.iter().map(|a| (a.action.player_index, a.action.action_type.clone())).collect()
+        };
+        let Json(history) = hand_history(Query(HandHistoryQuery { phase: None })).await;
+        let history = history.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].actions.len(), 8);
+        assert_eq!(
+            seats_and_actions(&history[0])[..3],
+            [(0, ActionType::Raise), (1, ActionType::Call), (2, ActionType::Fold)]
+        );
+
+        // The hand has reached the river, but its flop actions are still found.
+        let Json(flop) = hand_history(Query(HandHistoryQuery { phase: Some(GamePhase::Flop) })).await;
+        let flop = flop.unwrap();
+        assert_eq!(flop.len(), 1);
+        assert_eq!(seats_and_actions(&flop[0]), [(1, ActionType::Check), (0, ActionType::Check)]);
+        let Json(turn) = hand_history(Query(HandHistoryQuery { phase: Some(GamePhase::Turn) })).await;
+        assert_eq!(turn.unwrap()[0].actions.len(), 3);
+        let Json(river) = hand_history(Query(HandHistoryQuery { phase: Some(GamePhase::River) })).await;
+        assert!(river.unwrap().is_empty(), "nobody has acted on the river yet");
+    }
+
+    #[tokio::test]
+    async fn recorded_actions_are_timestamped_in_order() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = test_game(3);
+        for (player_index, action_type) in [(0, ActionType::Call), (1, ActionType::Call), (2, ActionType::Check)] {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            game.handle_action(Action { player_index, action_type, amount: None }).unwrap();
+        }
+        *GAME_STATE.lock().await = Some(game);
+
+        let Json(history) = hand_history(Query(HandHistoryQuery { phase: None })).await;
+        let hand = history.unwrap().pop().unwrap();
+        let mut previous = hand.timestamp;
+        for action in &hand.actions {
+            assert!(action.timestamp >= previous);
+            assert_eq!(action.elapsed_ms, (action.timestamp - previous).num_milliseconds() as u64);
+            assert!(action.elapsed_ms >= 5, "{}ms", action.elapsed_ms);
+            previous = action.timestamp;
+        }
+        let json = serde_json::to_value(&hand.actions[0]).unwrap();
+        assert!(json.get("timestamp").is_some() && json.get("elapsed_ms").is_some());
+    }
+
+    #[test]
+    fn handle_action_records_bet_into_hand_history() {
+        let mut game = GameState::new(&humans_only(2)).unwrap();
+        assert_eq!(game.hand_history.len(), 1);
+
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(50) })
+            .unwrap();
+
+        let hand = game.hand_history.last().unwrap();
+        assert_eq!(hand.actions.len(), 1);
+        assert_eq!(hand.actions[0].action.action_type, ActionType::Raise);
+        assert_eq!(hand.actions[0].action.amount, Some(50));
+        assert_eq!(hand.actions[0].phase, GamePhase::PreFlop);
+        assert_eq!(hand.pot_size, 60, "the raise to 50 and the big blind");
+        assert_eq!(hand.player_cards.len(), 2);
+    }
+
+    #[test]
+    fn card_display_uses_short_notation() {
+        assert_eq!(card(Rank::Ace, Suit::Hearts).to_string(), "Ah");
+        assert_eq!(card(Rank::King, Suit::Spades).to_string(), "Ks");
+        assert_eq!(card(Rank::Ten, Suit::Diamonds).to_string(), "Td");
+        assert_eq!(card(Rank::Two, Suit::Clubs).to_string(), "2c");
+    }
+
+    #[test]
+    fn pokerstars_export_of_a_blinds_hand() {
+        let names = vec!["You".to_string(), "Bob".to_string()];
+        let mut game = GameState::new(&NewGameQuery { player_names: Some(names), ..humans_only(2) }).unwrap();
+        // Heads-up the button posts the small blind and acts first.
+        game.players[0].cards = cards("Ah Ks");
+        let act = |player_index, action_type, amount| Action { player_index, action_type, amount };
+        game.handle_action(act(0, ActionType::Raise, Some(40))).unwrap();
+        game.handle_action(act(1, ActionType::Fold, None)).unwrap();
+        let hand = game.hand_history.last_mut().unwrap();
+        hand.timestamp = chrono::DateTime::parse_from_rfc3339("2024-03-01T18:30:00Z").unwrap().into();
+
+        let text = to_pokerstars_format(game.hand_history.last().unwrap(), 1, &game.players, Variant::Holdem, BettingStructure::PotLimit);
+        let expected = "\
+PokerStars Hand #1: Hold'em Pot Limit - 2024/03/01 18:30:00 UTC
+Table 'PokerBot' 2-max Seat #1 is the button
+Seat 1: You (1000 in chips)
+Seat 2: Bob (1000 in chips)
+You: posts small blind 5
+Bob: posts big blind 10
+*** HOLE CARDS ***
+Dealt to You [Ah Ks]
+You: raises 30 to 40
+Bob: folds
+You collected 50 from pot
+*** SUMMARY ***
+Total pot 50 | Rake 0
+Seat 1: You won (50)
+Seat 2: Bob folded
+";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn pokerstars_export_collects_each_share_of_a_split_pot() {
+        let mut game = test_game(2);
+        rig_hand(&mut game, &[cards("Ac 2d"), cards("Ad 3c")], &cards("Kh Qd Js Th 4c"));
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(100) }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        assert_eq!(game.showdown().unwrap(), vec![0, 1]);
+
+        let text = to_pokerstars_format(game.hand_history.last().unwrap(), 1, &game.players, game.variant, game.betting_structure);
+        let names: Vec<String> = game.players.iter().map(|p| p.name.clone()).collect();
+        assert!(text.contains(&format!("{} collected 100 from pot\n{} collected 100 from pot", names[0], names[1])), "{}", text);
+        assert!(text.contains(&format!("Seat 2: {} won (100)", names[1])), "{}", text);
+    }
+
+    #[test]
+    fn stats_csv_has_header_and_quoted_rows() {
+        let mut game = test_game(2);
+        game.stats.players[0] = PlayerStats {
+            games_played: 3,
+            games_won: 1,
+            total_profit: -40,
+            biggest_pot: 120,
+            best_hand: "Two Pair, Kings and Nines".to_string(),
+            favorite_action: "Aggressive".to_string(),
+            rebuys: 0,
+        };
+
+        let csv = stats_to_csv(&game.stats);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "player,games_played,games_won,total_profit,biggest_pot,best_hand,favorite_action");
+        assert_eq!(lines[1], "0,3,1,-40,120,\"Two Pair, Kings and Nines\",Aggressive");
+        assert_eq!(lines[2], "1,1,0,0,0,,");
+    }
+
+    #[tokio::test]
+    async fn stats_csv_endpoint_sets_content_type() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        *GAME_STATE.lock().await = Some(test_game(2));
+        let response = stats_csv().await.unwrap().into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+    }
+
+    /// Deals the given hole cards and a full board into the current hand.
+    fn rig_hand(game: &mut GameState, hands: &[Vec<Card>], board: &[Card]) {
+        for (player, cards) in game.players.iter_mut().zip(hands) {
+            player.cards = cards.clone();
+        }
+        game.community_cards = board.to_vec();
+    }
+
+    #[test]
+    fn showdown_tracks_wins_games_and_profit() {
+        let mut game = GameState::new(&humans_only(2)).unwrap();
+        let aces = vec![card(Rank::Ace, Suit::Spades), card(Rank::Ace, Suit::Hearts)];
+        let junk = vec![card(Rank::Seven, Suit::Clubs), card(Rank::Two, Suit::Diamonds)];
+        let board = vec![
+            card(Rank::King, Suit::Clubs),
+            card(Rank::Nine, Suit::Diamonds),
+            card(Rank::Four, Suit::Hearts),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Three, Suit::Clubs),
+        ];
+
+        // Hand one: the aces win a 200-chip pot.
+        rig_hand(&mut game, &[aces.clone(), junk.clone()], &board);
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(100) }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        assert_eq!(game.stats.total_hands, 0, "pot stats update at hand completion, not per action");
+        assert_eq!(game.showdown().unwrap(), vec![0]);
+        assert_eq!(game.players[0].chips, 1100);
+        assert_eq!(game.hand_history.last().unwrap().winner, Some(0));
+
+        // Hand two: the cards are swapped and seat one, now on the button, wins a 100-chip pot.
+        game.deal_new_hand().unwrap();
+        rig_hand(&mut game, &[junk, aces], &board);
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Raise, amount: Some(50) }).unwrap();
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        assert_eq!(game.showdown().unwrap(), vec![1]);
+
+        let stats = &game.stats.players;
+        assert_eq!((stats[0].games_played, stats[0].games_won, stats[0].total_profit), (2, 1, 50));
+        assert_eq!((stats[1].games_played, stats[1].games_won, stats[1].total_profit), (2, 1, -50));
+        assert_eq!(game.players[0].chips + game.players[1].chips, 2000);
+
+        // Pots of 200 and 100 chips.
+        assert_eq!(game.stats.total_hands, 2);
+        assert_eq!(game.stats.average_pot, 150);
+        assert_eq!(game.stats.biggest_pot, 200);
+    }
+
+    #[test]
+    fn new_hand_deals_fresh_cards_and_posts_blinds() {
+        let mut game = GameState::new(&humans_only(3)).unwrap();
+        // The opening hand posts blinds too: the button is seat 0, so seat 1 posts the small
+        // blind, seat 2 the big blind, and the button acts first.
+        assert_eq!((game.dealer, game.blind_seats, game.current_player), (0, Some((1, 2)), 0));
+        assert_eq!((game.pot, game.current_bet), (15, 10));
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(100) }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        game.handle_action(Action { player_index: 2, action_type: ActionType::Call, amount: None }).unwrap();
+        assert!(game.deal_new_hand().is_err(), "the pot must be settled first");
+        game.showdown().unwrap();
+
+        game.deal_new_hand().unwrap();
+        assert_eq!(game.hand_history.len(), 2);
+        assert!(game.community_cards.is_empty());
+        assert!(game.players.iter().all(|p| p.cards.len() == 2));
+        assert_eq!(game.deck.len(), 52 - 6);
+        let mut dealt: Vec<Card> = game.players.iter().flat_map(|p| p.cards.clone()).collect();
+        dealt.extend(game.deck.iter().cloned());
+        for (i, c) in dealt.iter().enumerate() {
+            assert!(!dealt[i + 1..].contains(c), "{} dealt twice", c);
+        }
+
+        // The button moved to seat 1, so seat 2 posts the small blind and seat 0 the big blind.
+        assert_eq!(game.dealer, 1);
+        assert_eq!((game.players[2].current_bet, game.players[0].current_bet), (5, 10));
+        assert_eq!(game.pot, 15);
+        assert_eq!(game.current_bet, 10);
+        assert_eq!(game.current_player, 1);
+        let chips: u32 = game.players.iter().map(|p| p.chips).sum();
+        assert_eq!(chips + game.pot, 3000);
+    }
+
+    #[test]
+    fn new_hand_skips_busted_players() {
+        let mut game = GameState::new(&humans_only(3)).unwrap();
+        // Everyone folds to the big blind, then seat 1 goes bust between hands.
+        for seat in [0, 1] {
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Fold, amount: None }).unwrap();
+        }
+        game.players[1].chips = 0;
+        game.deal_new_hand().unwrap();
+        assert!(game.players[1].cards.is_empty());
+        assert_eq!(game.players[0].cards.len(), 2);
+        assert_eq!(game.players[2].cards.len(), 2);
+        assert_eq!(game.stats.players[1].games_played, 1);
+        assert_eq!(game.stats.players[0].games_played, 2);
+    }
+
+    #[test]
+    fn stacks_set_each_seats_starting_chips() {
+        let config = NewGameQuery { num_players: 3, stacks: Some(vec![500, 1500, 1000]), ..Default::default() };
+        let game = GameState::new(&config).unwrap();
+        // Seats 1 and 2 have already posted the blinds.
+        let chips: Vec<u32> = game.players.iter().map(|p| p.chips + p.current_bet).collect();
+        assert_eq!(chips, [500, 1500, 1000]);
+
+        let short = NewGameQuery { num_players: 3, stacks: Some(vec![500, 1500]), ..Default::default() };
+        assert!(GameState::new(&short).is_err());
+        let empty_seat = NewGameQuery { num_players: 3, stacks: Some(vec![500, 0, 1000]), ..Default::default() };
+        assert!(GameState::new(&empty_seat).is_err());
+    }
+
+    #[test]
+    fn player_names_override_the_seat_defaults() {
+        let names = vec!["Hardik".to_string(), " Robo Rita ".to_string()];
+        let config = NewGameQuery { player_names: Some(names), ..Default::default() };
+        let game = GameState::new(&config).unwrap();
+        let player = serde_json::to_value(&game.players[0]).unwrap();
+        assert_eq!(player["name"], "Hardik");
+        assert_eq!(game.players[1].name, "Robo Rita");
+        assert!(game.players[1].is_robot);
+
+        let short = NewGameQuery { player_names: Some(vec!["Hardik".to_string()]), ..Default::default() };
+        assert!(GameState::new(&short).is_err());
+        let blank = NewGameQuery { player_names: Some(vec!["Hardik".to_string(), "  ".to_string()]), ..Default::default() };
+        assert!(GameState::new(&blank).is_err());
+    }
+
+    #[tokio::test]
+    async fn five_card_draw_deals_no_board() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let config = NewGameQuery { variant: Variant::FiveCardDraw, ..Default::default() };
+        let game = GameState::new(&config).unwrap();
+        let deck = game.deck.clone();
+        *GAME_STATE.lock().await = Some(game);
+
+        assert!(deal_flop().await.0.community_cards.is_empty());
+        assert!(deal_turn().await.0.community_cards.is_empty());
+        assert!(deal_river().await.0.community_cards.is_empty());
+        assert_eq!(GAME_STATE.lock().await.as_ref().unwrap().deck, deck);
+    }
+
+    #[test]
+    fn five_card_draw_replaces_the_discards() {
+        let config = NewGameQuery { variant: Variant::FiveCardDraw, ..Default::default() };
+        let mut game = GameState::new(&config).unwrap();
+        assert!(game.players.iter().all(|p| p.cards.len() == 5));
+        assert!(game.draw(0, &[1, 3]).is_err(), "nobody has bet yet");
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Check, amount: None }).unwrap();
+        assert_eq!(game.deal_next_street(), Err(GameError::NoStreetToDeal));
+
+        let before = game.players[0].cards.clone();
+        let top = game.deck[game.deck.len() - 2..].to_vec();
+        let drawn = game.draw(0, &[1, 3]).unwrap();
+        assert_eq!(drawn, [top[1], top[0]]);
+        let after = &game.players[0].cards;
+        assert_eq!((after[1], after[3]), (drawn[0], drawn[1]));
+        assert_eq!((after[0], after[2], after[4]), (before[0], before[2], before[4]));
+        assert!(game.discards.contains(&before[1]) && game.discards.contains(&before[3]));
+        assert!(game.check_deck_integrity().is_ok());
+        assert!(game.draw(0, &[0]).is_err(), "one draw per hand");
+        assert!(game.draw(1, &[2, 2]).is_err());
+        assert_eq!(game.draw(1, &[]).unwrap(), vec![]);
+
+        // The draw opens a second betting round, which has to finish before the showdown.
+        assert_eq!(game.street(), GamePhase::AfterDraw);
+        assert_eq!((game.current_player, game.current_bet), (1, 0));
+        assert!(game.showdown().is_err());
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Bet, amount: Some(20) }).unwrap();
+        assert!(game.showdown().is_err(), "the bet is still to be answered");
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        let phases: Vec<GamePhase> = game.hand_history.last().unwrap().actions.iter().map(|a| a.phase.clone()).collect();
+        assert_eq!(phases, [GamePhase::PreFlop, GamePhase::PreFlop, GamePhase::AfterDraw, GamePhase::AfterDraw]);
+
+        game.showdown().unwrap();
+        assert!(game.community_cards.is_empty());
+    }
+
+    #[test]
+    fn robots_stand_pat_once_the_betting_before_the_draw_is_over() {
+        let config = NewGameQuery { variant: Variant::FiveCardDraw, game_mode: GameMode::RobotPlay, ..Default::default() };
+        let mut game = GameState::new(&config).unwrap();
+        while !game.betting_round_complete() {
+            let legal = game.legal_actions(0).unwrap();
+            let action_type = if legal.actions.contains(&ActionType::Check) { ActionType::Check } else { ActionType::Call };
+            game.handle_action(Action { player_index: 0, action_type, amount: None }).unwrap();
+            game.advance_until_human().unwrap();
+        }
+        assert_eq!(game.drawn, vec![1], "the robot drew without waiting for the human");
+        assert_eq!(game.street(), GamePhase::PreFlop);
+
+        game.draw(0, &[0, 1]).unwrap();
+        assert_eq!(game.street(), GamePhase::AfterDraw);
+        assert!(!game.betting_round_complete());
+    }
+
+    #[test]
+    fn busted_human_can_rebuy_into_the_next_hand() {
+        let config = NewGameQuery { allow_rebuy: true, rebuy_amount: 500, ..Default::default() };
+        let mut game = GameState::new(&config).unwrap();
+        assert!(matches!(game.rebuy(0), Err(GameError::NotBusted)));
+
+        // The human folds the opening hand and is busted before the next, which ends the game.
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Fold, amount: None }).unwrap();
+        game.players[1].chips += game.players[0].chips;
+        game.players[0].chips = 0;
+        game.players[0].cards.clear();
+        assert_eq!(game.check_game_over(), Some(1));
+        assert!(game.deal_new_hand().is_err());
+
+        game.rebuy(0).unwrap();
+        assert_eq!(game.players[0].chips, 500);
+        assert_eq!(game.stats.players[0].rebuys, 1);
+        assert_eq!(game.winner, None);
+        game.deal_new_hand().unwrap();
+        assert_eq!(game.players[0].cards.len(), 2);
+
+        let mut no_rebuys = test_game(2);
+        no_rebuys.players[0].chips = 0;
+        assert!(matches!(no_rebuys.rebuy(0), Err(GameError::RebuyNotAllowed)));
+    }
+
+    #[test]
+    fn last_player_standing_wins_the_pot_uncontested() {
+        let mut game = GameState::new(&humans_only(4)).unwrap();
+        let first = game.current_player;
+        game.handle_action(Action { player_index: first, action_type: ActionType::Raise, amount: Some(100) }).unwrap();
+        let stack_after_bet = game.players[first].chips;
+        for _ in 0..3 {
+            let seat = game.current_player;
+            assert_ne!(seat, first);
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Fold, amount: None }).unwrap();
+        }
+
+        assert!(game.community_cards.is_empty(), "no board is dealt");
+        assert_eq!(game.pot, 0);
+        // The raise comes back along with both blinds.
+        assert_eq!(game.players[first].chips, stack_after_bet + 115);
+        assert_eq!(game.stats.players[first].games_won, 1);
+        assert_eq!(game.stats.total_hands, 1);
+        let hand = game.hand_history.last().unwrap();
+        assert_eq!(hand.phase, GamePhase::Showdown);
+        assert_eq!(hand.winner, Some(first));
+        assert_eq!(hand.pot_size, 115);
+        assert!(game.showdown().is_err(), "the hand is already settled");
+        game.deal_new_hand().unwrap();
+    }
+
+    #[test]
+    fn odd_chip_goes_to_the_first_winner_left_of_the_button() {
+        let mut game = GameState::new(&humans_only(3)).unwrap();
+        // Seats 0 and 2 both play the board's broadway straight; seat 1 folds its blind.
+        rig_hand(&mut game, &[cards("2c 3d"), cards("7s 8s"), cards("4c 5d")], &cards("Ah Kd Qs Jc Th"));
+        let act = |player_index, action_type, amount| Action { player_index, action_type, amount };
+        for action in [act(0, ActionType::Raise, Some(50)), act(1, ActionType::Fold, None), act(2, ActionType::Call, None)] {
+            game.handle_action(action).unwrap();
+        }
+        assert_eq!(game.pot, 105);
+
+        assert_eq!(game.showdown().unwrap(), vec![0, 2]);
+        let won = |seat: usize| game.hand_history.last().unwrap().showdown_results.iter()
+            .find(|r| r.player_index == seat).unwrap().amount_won;
+        // Seat 2 is closer to the button's left than seat 0, which is the button itself.
+        assert_eq!(won(2), 53);
+        assert_eq!(won(0), 52);
+    }
+
+    #[test]
+    fn the_rake_comes_out_before_the_winner_is_paid() {
+        let config = NewGameQuery { num_players: 2, rake_percent: 0.05, rake_cap: 8, ..Default::default() };
+        let mut game = GameState::new(&config).unwrap();
+        assert_eq!(game.rake_for(100), 5);
+        assert_eq!(game.rake_for(1000), 8, "capped");
+        rig_hand(&mut game, &[cards("Ac Ad"), cards("7c 2d")], &cards("Kh 9c 8d 4s 3h"));
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(50) }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+
+        assert_eq!(game.showdown().unwrap(), vec![0]);
+        assert_eq!(game.players[0].chips, 1000 - 50 + 100 - 5);
+        assert_eq!(game.stats.total_rake, 5);
+        assert_eq!(game.total_chips(), 2000 - 5, "the rake leaves play");
+        assert_eq!(game.stats.players[0].total_profit, 45);
+        let hand = game.hand_history.last().unwrap();
+        assert_eq!(hand.rake, 5);
+        let text = to_pokerstars_format(hand, 1, &game.players, game.variant, game.betting_structure);
+        assert!(text.contains("Total pot 100 | Rake 5"), "{}", text);
+    }
+
+    #[test]
+    fn side_pots_cap_what_a_short_stack_can_win() {
+        let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 1000, 100]), ..humans_only(3) }).unwrap();
+        // The short stack has the best hand but only covered 100 of each bet.
+        rig_hand(&mut game, &[cards("Kc Kd"), cards("Qc Qd"), cards("Ac Ad")], &cards("2h 7s 9d Jc 4h"));
+        let act = |player_index, action_type, amount| Action { player_index, action_type, amount };
+        for action in [act(0, ActionType::Raise, Some(300)), act(1, ActionType::Call, None), act(2, ActionType::Call, None)] {
+            game.handle_action(action).unwrap();
+        }
+        assert_eq!(game.side_pots(), vec![(300, vec![0, 1, 2]), (400, vec![0, 1])]);
+
+        game.showdown().unwrap();
+        let results = &game.hand_history.last().unwrap().showdown_results;
+        let won = |seat: usize| results.iter().find(|r| r.player_index == seat).unwrap().amount_won;
+        assert_eq!(won(2), 300, "aces win the main pot");
+        assert_eq!(won(0), 400, "kings win the side pot");
+        assert_eq!(won(1), 0);
+    }
+
+    #[test]
+    fn game_is_over_when_one_player_holds_all_chips() {
+        let mut game = GameState::new(&humans_only(3)).unwrap();
+        assert_eq!(game.is_game_over(), None);
+
+        // Everyone is all-in, and seat zero wins every stack at showdown.
+        let aces = vec![card(Rank::Ace, Suit::Spades), card(Rank::Ace, Suit::Hearts)];
+        let junk = vec![card(Rank::Seven, Suit::Clubs), card(Rank::Two, Suit::Diamonds)];
+        let more_junk = vec![card(Rank::Eight, Suit::Clubs), card(Rank::Two, Suit::Hearts)];
+        let board = vec![
+            card(Rank::King, Suit::Clubs),
+            card(Rank::Nine, Suit::Diamonds),
+            card(Rank::Four, Suit::Hearts),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Three, Suit::Clubs),
+        ];
+        rig_hand(&mut game, &[aces, junk, more_junk], &board);
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(1000) }).unwrap();
+        for seat in [1, 2] {
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Call, amount: None }).unwrap();
+        }
+        assert_eq!(game.is_game_over(), None, "chips are still in the pot");
+        game.showdown().unwrap();
+
+        assert_eq!(game.is_game_over(), Some(0));
+        assert_eq!(game.winner, Some(0));
+        assert!(game.stats.end_time.is_some());
+        assert!(game.deal_new_hand().is_err());
+        let json = serde_json::to_value(&game).unwrap();
+        assert_eq!(json["winner"], 0);
+    }
+
+    #[test]
+    fn bets_that_would_overflow_the_pot_are_rejected() {
+        let mut game = GameState::new(&humans_only(2)).unwrap();
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(100) }).unwrap();
+        game.players[1].chips = u32::MAX;
+
+        let result = game.handle_action(Action { player_index: 1, action_type: ActionType::Raise, amount: Some(u32::MAX) });
+        assert_eq!(result, Err(GameError::PotOverflow));
+        assert_eq!(game.pot, 110);
+        assert_eq!(game.players[1].chips, u32::MAX);
+        assert_eq!(game.players[1].current_bet, 10);
+    }
+
+    #[test]
+    fn bets_beyond_the_stack_are_rejected_but_all_in_is_allowed() {
+        let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 310]), ..humans_only(2) }).unwrap();
+        let result = game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(1001) });
+        assert_eq!(result, Err(GameError::RaiseTooLarge { verb: "Raise", amount: 1001, all_in: 1000 }));
+        assert_eq!((game.players[0].chips, game.pot), (995, 15));
+
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(1000) }).unwrap();
+        assert_eq!((game.players[0].chips, game.pot), (0, 1010));
+
+        // Calling with a short stack puts the caller all-in for less.
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        assert_eq!((game.players[1].chips, game.pot), (0, 1310));
+    }
+
+    /// A three-handed game of humans that has just posted blinds: the button is seat 0,
+    /// seat 1 is the small blind, seat 2 the big blind, and seat 0 acts first.
+    fn three_handed_after_blinds() -> GameState {
+        let game = GameState::new(&humans_only(3)).unwrap();
+        assert_eq!((game.dealer, game.current_player), (0, 0));
+        game
+    }
+
+    /// `three_handed_after_blinds` under the given betting structure.
+    fn three_handed_with(betting_structure: BettingStructure) -> GameState {
+        GameState::new(&NewGameQuery { betting_structure, ..humans_only(3) }).unwrap()
+    }
+
+    #[test]
+    fn pot_limit_caps_raises_at_the_pot() {
+        let raise_to = |amount| Action { player_index: 0, action_type: ActionType::Raise, amount: Some(amount) };
+
+        // Facing the 10 big blind with 15 in the pot: call 10, then raise the 25 pot, to 35.
+        let mut pot_limit = three_handed_with(BettingStructure::PotLimit);
+        assert_eq!(pot_limit.legal_actions(0).unwrap().max_raise, 35);
+        assert_eq!(
+            pot_limit.handle_action(raise_to(36)),
+            Err(GameError::OverLimit { verb: "Raise", amount: 36, max: 35 })
+        );
+        pot_limit.handle_action(raise_to(35)).unwrap();
+        assert_eq!(pot_limit.pot, 50);
+
+        let mut no_limit = three_handed_with(BettingStructure::NoLimit);
+        no_limit.handle_action(raise_to(36)).unwrap();
+        assert_eq!(no_limit.current_bet, 36);
+    }
+
+    #[test]
+    fn fixed_limit_bets_come_in_fixed_increments() {
+        let mut game = three_handed_with(BettingStructure::FixedLimit);
+        let legal = game.legal_actions(0).unwrap();
+        assert_eq!((legal.min_raise, legal.max_raise), (20, 20));
+        let raise = Action { player_index: 0, action_type: ActionType::Raise, amount: Some(30) };
+        assert_eq!(game.handle_action(raise), Err(GameError::OverLimit { verb: "Raise", amount: 30, max: 20 }));
+
+        game.deal_flop();
+        game.deal_turn();
+        let legal = game.legal_actions(1).unwrap();
+        assert_eq!((legal.min_raise, legal.max_raise), (20, 20), "the big bet is double on the turn");
+    }
+
+    #[test]
+    fn big_blind_may_check_their_option() {
+        let mut game = three_handed_after_blinds();
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        assert_eq!(game.current_player, 2);
+        game.handle_action(Action { player_index: 2, action_type: ActionType::Check, amount: None }).unwrap();
+        assert_eq!(game.pot, 30);
+    }
+
+    #[test]
+    fn check_facing_the_big_blind_is_rejected() {
+        let mut game = three_handed_after_blinds();
+        let result = game.handle_action(Action { player_index: 0, action_type: ActionType::Check, amount: None });
+        assert_eq!(result, Err(GameError::IllegalCheck));
+        assert_eq!(game.current_player, 0);
+    }
+
+    #[test]
+    fn actions_from_seats_not_on_the_clock_are_rejected() {
+        let mut game = three_handed_after_blinds();
+        let waiting_on = game.players[0].name.clone();
+        let call = |player_index| Action { player_index, action_type: ActionType::Call, amount: None };
+        assert_eq!(game.handle_action(call(1)), Err(GameError::OutOfTurn { waiting_on: waiting_on.clone() }));
+        assert_eq!(game.hand_history.last().unwrap().actions.len(), 0);
+        game.handle_action(call(0)).unwrap();
+
+        // A folded seat has no turn to take, even when the clock comes back round to it.
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Fold, amount: None }).unwrap();
+        game.current_player = 1;
+        assert!(matches!(game.handle_action(call(1)), Err(GameError::OutOfTurn { .. })));
+    }
+
+    #[test]
+    fn bets_reset_when_the_next_street_is_dealt() {
+        let mut game = three_handed_after_blinds();
+        for seat in [0, 1] {
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Call, amount: None }).unwrap();
+        }
+        game.handle_action(Action { player_index: 2, action_type: ActionType::Check, amount: None }).unwrap();
+        game.deal_flop();
+        assert_eq!(game.current_bet, 0);
+        assert!(game.players.iter().all(|p| p.current_bet == 0));
+        let seat = game.current_player;
+        game.handle_action(Action { player_index: seat, action_type: ActionType::Bet, amount: Some(10) }).unwrap();
+    }
+
+    #[tokio::test]
+    async fn legal_actions_facing_a_bet_exclude_check() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = three_handed_after_blinds();
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(40) }).unwrap();
+        *GAME_STATE.lock().await = Some(game);
+
+        // Seat 1 has the 5-chip small blind in and faces a raise to 40.
+        let Json(legal) = legal_actions().await;
+        let legal = legal.unwrap();
+        assert_eq!(legal.player_index, 1);
+        assert!(!legal.actions.contains(&ActionType::Check));
+        assert!(legal.actions.contains(&ActionType::Call));
+        assert!(legal.actions.contains(&ActionType::Raise));
+        assert!(!legal.actions.contains(&ActionType::Bet));
+        assert_eq!(legal.call_amount, 35);
+        // The raise was 30 on top of the big blind, so a re-raise must add at least 30 more.
+        assert_eq!(legal.min_raise, 70);
+        assert_eq!(legal.max_raise, 1000);
+    }
+
+    #[test]
+    fn handle_action_enforces_the_advertised_legal_actions() {
+        let mut game = three_handed_after_blinds();
+        let legal = game.legal_actions(0).unwrap();
+        assert_eq!(legal.actions, vec![ActionType::Fold, ActionType::Call, ActionType::Raise]);
+        let result = game.handle_action(Action { player_index: 0, action_type: ActionType::Bet, amount: Some(40) });
+        assert!(result.is_err());
+        let result = game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(legal.max_raise + 1) });
+        assert_eq!(result, Err(GameError::RaiseTooLarge { verb: "Raise", amount: 1001, all_in: 1000 }));
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(legal.max_raise) }).unwrap();
+        assert_eq!(game.players[0].chips, 0);
+    }
+
+    #[test]
+    fn omaha_must_use_exactly_two_hole_cards() {
+        // Four hearts on the board and a single heart in hand: a flush in Hold'em, but Omaha
+        // can't play three board hearts plus one hole heart.
+        let hole = vec![
+            card(Rank::Queen, Suit::Hearts),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Jack, Suit::Diamonds),
+            card(Rank::Ten, Suit::Clubs),
+        ];
+        let board = vec![
+            card(Rank::Ace, Suit::Hearts),
+            card(Rank::King, Suit::Hearts),
+            card(Rank::Seven, Suit::Hearts),
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Nine, Suit::Clubs),
+        ];
+        assert_eq!(Variant::Holdem.best_hand(&hole[..2], &board).unwrap().hand_type, HandType::Flush);
+        assert_eq!(Variant::Omaha.best_hand(&hole, &board).unwrap().hand_type, HandType::Pair);
+    }
+
+    #[test]
+    fn omaha_cannot_play_the_board() {
+        // A board straight plays for everyone in Hold'em but not in Omaha.
+        let hole = vec![
+            card(Rank::Two, Suit::Clubs),
+            card(Rank::Three, Suit::Diamonds),
+            card(Rank::Four, Suit::Spades),
+            card(Rank::Seven, Suit::Clubs),
+        ];
+        let board = vec![
+            card(Rank::Ace, Suit::Hearts),
+            card(Rank::King, Suit::Diamonds),
+            card(Rank::Queen, Suit::Hearts),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Ten, Suit::Clubs),
+        ];
+        assert_eq!(Variant::Holdem.best_hand(&hole[..2], &board).unwrap().hand_type, HandType::Straight);
+        assert_eq!(Variant::Omaha.best_hand(&hole, &board).unwrap().hand_type, HandType::HighCard);
+    }
+
+    #[test]
+    fn omaha_games_deal_four_hole_cards() {
+        let mut game = GameState::new(&NewGameQuery { variant: Variant::Omaha, ..humans_only(3) }).unwrap();
+        assert!(game.players.iter().all(|p| p.cards.len() == 4));
+        assert_eq!(game.deck.len(), 52 - 12);
+        assert!(game.players.iter().all(|p| p.win_probability > 0.0));
+
+        for seat in [0, 1] {
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Fold, amount: None }).unwrap();
+        }
+        game.deal_new_hand().unwrap();
+        assert!(game.players.iter().all(|p| p.cards.len() == 4));
+    }
+
+    #[test]
+    fn royal_flush_outranks_king_high_straight_flush() {
+        let royal = evaluate_hand(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::King, Suit::Spades),
+            card(Rank::Queen, Suit::Spades),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Ten, Suit::Spades),
+        ]);
+        let king_high = evaluate_hand(&[
+            card(Rank::King, Suit::Hearts),
+            card(Rank::Queen, Suit::Hearts),
+            card(Rank::Jack, Suit::Hearts),
+            card(Rank::Ten, Suit::Hearts),
+            card(Rank::Nine, Suit::Hearts),
+        ]);
+        let steel_wheel = evaluate_hand(&[
+            card(Rank::Ace, Suit::Clubs),
+            card(Rank::Two, Suit::Clubs),
+            card(Rank::Three, Suit::Clubs),
+            card(Rank::Four, Suit::Clubs),
+            card(Rank::Five, Suit::Clubs),
+        ]);
+        assert_eq!(royal.hand_type, HandType::RoyalFlush);
+        assert_eq!(king_high.hand_type, HandType::StraightFlush);
+        // The ace plays low in a steel wheel, so it is not a royal.
+        assert_eq!(steel_wheel.hand_type, HandType::StraightFlush);
+        assert!(royal > king_high);
+        assert!(king_high > steel_wheel);
+    }
+
+    #[test]
+    fn short_deck_straight_plays_the_ace_low() {
+        let hand = Variant::ShortDeck.best_hand(&cards("Ah 6d"), &cards("7c 8s 9h Kd Kc")).unwrap();
+        assert_eq!(hand.hand_type, HandType::Straight);
+        assert_eq!(hand.values, vec![9, 8, 7, 6, 1]);
+        assert!(hand < Variant::ShortDeck.best_hand(&cards("Th 6d"), &cards("7c 8s 9h Kd Kc")).unwrap());
+        assert!(hand > Variant::ShortDeck.best_hand(&cards("Ah Ad"), &cards("7c 8s Jh Kd Kc")).unwrap());
+        // In full-deck Hold'em the same cards are only a pair of kings.
+        assert_eq!(Variant::Holdem.best_hand(&cards("Ah 6d"), &cards("7c 8s 9h Kd Kc")).unwrap().hand_type, HandType::Pair);
+
+        let steel = Variant::ShortDeck.best_hand(&cards("Ah 6h"), &cards("7h 8h 9h Kd Kc")).unwrap();
+        assert_eq!(steel.hand_type, HandType::StraightFlush);
+    }
+
+    #[test]
+    fn short_deck_flush_beats_a_full_house() {
+        let board = cards("Kh Kd 9h 6h 7c");
+        let flush = Variant::ShortDeck.best_hand(&cards("Ah Th"), &board).unwrap();
+        let full_house = Variant::ShortDeck.best_hand(&cards("Ks 9s"), &board).unwrap();
+        assert_eq!(flush.hand_type, HandType::Flush);
+        assert_eq!(full_house.hand_type, HandType::FullHouse);
+        assert!(flush > full_house);
+        assert!(Variant::Holdem.best_hand(&cards("Ah Th"), &board).unwrap() < Variant::Holdem.best_hand(&cards("Ks 9s"), &board).unwrap());
+        // Quads still beat both.
+        assert!(Variant::ShortDeck.best_hand(&cards("Ks Kc"), &board).unwrap() > flush);
+    }
+
+    #[test]
+    fn short_deck_games_deal_from_36_cards() {
+        let config = NewGameQuery { num_players: 8, variant: Variant::ShortDeck, ..Default::default() };
+        let game = GameState::new(&config).unwrap();
+        let dealt = game.players.iter().flat_map(|p| &p.cards).chain(&game.deck);
+        assert_eq!(dealt.clone().count(), 36);
+        assert!(dealt.into_iter().all(|c| c.value() >= 6));
+    }
+
+    #[test]
+    #[should_panic(expected = "exactly 5 cards")]
+    fn evaluate_hand_rejects_six_cards() {
+        evaluate_hand(&cards("Ah Kh 9h 7h 4h 2h"));
+    }
+
+    #[test]
+    fn too_few_cards_make_no_hand() {
+        assert_eq!(evaluate_best_hand(&cards("Ah Kh 9h")), None);
+        assert_eq!(best_hand_cards(&cards("Ah Kh 9h 7h")), None);
+        assert_eq!(Variant::Holdem.best_hand(&[], &cards("Ah Kh 9h")), None);
+        // A folded player's empty hand just drops out of the showdown.
+        let share = showdown_share(&cards("As Ad"), &[vec![]], &cards("Kh 9c 7d 4s 2h"), Variant::Holdem);
+        assert_eq!(share, 1.0);
+    }
+
+    #[test]
+    fn six_suited_cards_make_a_flush_of_the_best_five() {
+        let six = cards("Ah Kh 9h 7h 4h 2h");
+        let (hand, five) = best_hand_cards(&six).unwrap();
+        assert_eq!(hand.hand_type, HandType::Flush);
+        assert_eq!(hand.values, vec![14, 13, 9, 7, 4]);
+        assert_eq!(five, cards("Ah Kh 9h 7h 4h"));
+        // Four of the suit among five cards is no flush.
+        assert_eq!(evaluate_hand(&cards("Ah Kh 9h 7h 4c")).hand_type, HandType::HighCard);
+    }
+
+    #[tokio::test]
+    async fn version_reports_the_crate_version() {
+        let Json(info) = version().await;
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_hash.is_empty());
+        assert!(info.built_at.is_some_and(|built| built <= chrono::Utc::now()));
+    }
+
+    #[test]
+    fn rankings_run_from_high_card_to_royal_flush() {
+        let rankings = hand_rankings();
+        assert_eq!(rankings.len(), 10);
+        assert_eq!(rankings[0].hand_type, HandType::HighCard);
+        assert_eq!(rankings[9].hand_type, HandType::RoyalFlush);
+        for pair in rankings.windows(2) {
+            assert!(pair[0].hand_type < pair[1].hand_type);
+            assert!(evaluate_hand(&pair[0].example) < evaluate_hand(&pair[1].example));
+        }
+        for ranking in &rankings {
+            assert_eq!(evaluate_hand(&ranking.example).hand_type, ranking.hand_type, "{}", ranking.example_name);
+        }
+        assert_eq!(rankings[6].example_name, "Full house, Tens full of Fours");
+    }
+
+    #[test]
+    fn wheel_is_found_among_seven_cards() {
+        // The king and queen outrank every wheel card, but only the wheel makes a straight.
+        let seven = cards("Ah Kd 2c Qs 3d 4h 5s");
+        let (hand, five) = best_hand_cards(&seven).unwrap();
+        assert_eq!(hand.hand_type, HandType::Straight);
+        assert_eq!(hand.values[0], 5);
+        assert_eq!(evaluate_best_hand(&seven).unwrap(), hand);
+        assert_eq!(describe_hand(&hand), "Straight, Five high");
+        assert!(cards("Ah 2c 3d 4h 5s").iter().all(|c| five.contains(c)));
+        // A six on board makes the six-high straight instead.
+        assert_eq!(evaluate_best_hand(&cards("Ah Kd 2c 6s 3d 4h 5s")).unwrap().values[0], 6);
+    }
+
+    #[test]
+    fn full_house_compares_trips_before_pair() {
+        let kings_full = evaluate_hand(&cards("Kh Kd Ks 2c 2d"));
+        let queens_full = evaluate_hand(&cards("Qh Qd Qs Ac Ad"));
+        assert_eq!(kings_full.values, vec![13, 13, 13, 2, 2]);
+        assert!(kings_full > queens_full);
+    }
+
+    #[test]
+    fn pair_compares_pair_rank_before_kickers() {
+        let threes = evaluate_hand(&cards("3h 3d 7s 5c 2d"));
+        let twos_ace_kicker = evaluate_hand(&cards("2h 2s As Kc Qd"));
+        assert_eq!(threes.values, vec![3, 3, 7, 5, 2]);
+        assert!(threes > twos_ace_kicker);
+
+        let nines_king = evaluate_hand(&cards("9h 9d Ks 5c 2d"));
+        let nines_queen = evaluate_hand(&cards("9s 9c Qs Jc Td"));
+        assert!(nines_king > nines_queen);
+    }
+
+    #[test]
+    fn two_pair_compares_top_pair_first() {
+        let jacks_and_threes = evaluate_hand(&cards("Jh Jd 3s 3c 2d"));
+        let tens_and_nines_ace = evaluate_hand(&cards("Th Td 9s 9c Ad"));
+        assert_eq!(jacks_and_threes.values, vec![11, 11, 3, 3, 2]);
+        assert!(jacks_and_threes > tens_and_nines_ace);
+    }
+
+    #[test]
+    fn two_pair_compares_low_pair_then_kicker() {
+        let kings_queens_five = evaluate_hand(&cards("Kh Kd Qs Qc 5d"));
+        let kings_jacks_ace = evaluate_hand(&cards("Ks Kc Jh Jd Ah"));
+        assert_eq!(kings_queens_five.values, vec![13, 13, 12, 12, 5]);
+        assert_eq!(kings_jacks_ace.values, vec![13, 13, 11, 11, 14]);
+        assert!(kings_queens_five > kings_jacks_ace, "the low pair outranks the kicker");
+
+        let nine_kicker = evaluate_hand(&cards("Kh Kd Qs Qc 9d"));
+        let seven_kicker = evaluate_hand(&cards("Ks Kc Qh Qd 7h"));
+        assert!(nine_kicker > seven_kicker);
+        assert_eq!(nine_kicker, evaluate_hand(&cards("Ks Kc Qh Qd 9c")));
+
+        // With three pairs on offer, the third pair's rank is only a kicker.
+        let three_pairs = evaluate_best_hand(&cards("Kh Kd Qs Qc 8d 8h 2c")).unwrap();
+        assert_eq!(three_pairs.values, vec![13, 13, 12, 12, 8]);
+        assert!(three_pairs < evaluate_best_hand(&cards("Kh Kd Qs Qc 9d 3h 2c")).unwrap());
+    }
+
+    #[test]
+    fn trips_and_quads_compare_the_set_first() {
+        let fives = evaluate_hand(&cards("5h 5d 5s 3c 2d"));
+        let fours_ace_king = evaluate_hand(&cards("4h 4d 4s Ac Kd"));
+        assert!(fives > fours_ace_king);
+
+        let quad_sixes = evaluate_hand(&cards("6h 6d 6s 6c 2d"));
+        let quad_fives_ace = evaluate_hand(&cards("5h 5d 5s 5c Ad"));
+        assert!(quad_sixes > quad_fives_ace);
+    }
+
+    #[test]
+    fn bind_address_parsing() {
+        assert_eq!(parse_bind_address("127.0.0.1:8080").unwrap(), "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(parse_bind_address(" 0.0.0.0:3000 ").unwrap().port(), 3000);
+        assert!(parse_bind_address("[::1]:4000").unwrap().is_ipv6());
+        assert!(parse_bind_address("localhost").is_err());
+        assert!(parse_bind_address("127.0.0.1:99999").is_err());
+        assert!(parse_bind_address("").is_err());
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_rejected() {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::post("/simulate")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(vec![b' '; MAX_REQUEST_BODY_BYTES + 1]))
+            .unwrap();
+        let response = router(CorsLayer::new()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn cors_origins_come_from_the_allow_list() {
+        // Applying the layer runs tower-http's checks, which panic on invalid combinations
+        // such as credentials with wildcard headers.
+        let apply = |cors: CorsLayer| {
+            let _: Router = Router::new().route("/", get(|| async {})).layer(cors);
+        };
+        apply(cors_layer(None).unwrap());
+        apply(cors_layer(Some("http://localhost:5173, https://poker.example.com".to_string())).unwrap());
+        apply(cors_layer(Some("https://poker.example.com,".to_string())).unwrap());
+
+        let err = cors_layer(Some("http://localhost:5173,not an origin".to_string())).unwrap_err();
+        assert!(err.contains("not an origin"), "{}", err);
+        assert!(cors_layer(Some("*".to_string())).is_err());
+        assert!(cors_layer(Some("https://bad\nheader.com".to_string())).is_err());
+        assert!(cors_layer(Some(" , ".to_string())).is_err());
+    }
+
+    #[test]
+    fn bind_address_prefers_args_then_env_then_default() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(bind_address(&[], None).unwrap().to_string(), DEFAULT_ADDR);
+        assert_eq!(bind_address(&[], Some("127.0.0.1:4000".into())).unwrap().port(), 4000);
+        assert_eq!(bind_address(&args(&["--addr", "127.0.0.1:5000"]), Some("127.0.0.1:4000".into())).unwrap().port(), 5000);
+        assert_eq!(bind_address(&args(&["--addr=127.0.0.1:6000"]), None).unwrap().port(), 6000);
+        assert!(bind_address(&args(&["--addr"]), None).is_err());
+        assert!(bind_address(&[], Some("nonsense".into())).is_err());
+    }
+
+    #[tokio::test]
+    async fn deck_endpoint_reflects_the_flop() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        std::env::set_var("POKERBOT_DEBUG", "1");
+        let mut game = test_game(4);
+        game.deal_flop();
+        let board = game.community_cards.clone();
+        *GAME_STATE.lock().await = Some(game);
+
+        let Json(deck) = get_deck().await.unwrap();
+        assert_eq!(deck.len(), 52 - 2 * 4 - 3);
+        assert!(board.iter().all(|c| !deck.contains(c)));
+    }
+
+    #[tokio::test]
+    async fn every_card_is_accounted_for_mid_hand() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        std::env::set_var("POKERBOT_DEBUG", "1");
+        let mut game = test_game(4);
+        game.deal_flop();
+        let folder = game.current_player;
+        game.handle_action(Action { player_index: folder, action_type: ActionType::Fold, amount: None }).unwrap();
+        game.deal_turn();
+        assert_eq!(game.discards.len(), 2, "the folded hand is still accounted for");
+        *GAME_STATE.lock().await = Some(game.clone());
+        let Json(check) = verify_deck().await.unwrap();
+        assert!(check.ok, "{:?}", check.discrepancy);
+
+        let lost = game.deck.pop().unwrap();
+        assert_eq!(game.check_deck_integrity(), Err(format!("Missing cards: {}", lost)));
+        game.deck.push(game.community_cards[0]);
+        assert!(game.check_deck_integrity().unwrap_err().starts_with("Duplicate card"));
+    }
+
+    #[tokio::test]
+    async fn benchmark_reports_throughput_without_touching_the_game() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        std::env::set_var("POKERBOT_DEBUG", "1");
+        let game = test_game(3);
+        *GAME_STATE.lock().await = Some(game.clone());
+
+        let Json(report) = benchmark(Query(BenchmarkQuery { hands: 5 })).await.unwrap();
+        assert_eq!(report.hands, 5);
+        assert!(report.hands_per_second > 0.0);
+        assert_eq!(GAME_STATE.lock().await.as_ref().unwrap().deck, game.deck);
+
+        let (status, _) = benchmark(Query(BenchmarkQuery { hands: 0 })).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn new_game_rejects_invalid_settings() {
+        for query in [
+            NewGameQuery { num_players: 1, ..Default::default() },
+            NewGameQuery { num_players: 9, ..Default::default() },
+            NewGameQuery { starting_chips: 0, ..Default::default() },
+        ] {
+            let (status, Json(body)) = new_game(Query(query)).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body["error"].is_string());
+        }
+        assert!(GameState::new(&NewGameQuery { num_players: 9, ..Default::default() })
+            .unwrap_err()
+            .contains("between 2 and 8"));
+    }
+
+    #[tokio::test]
+    async fn diff_after_an_action_carries_the_pot_but_not_the_deck() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = three_handed_after_blinds();
+        publish_state(&mut game);
+        let since = game.version;
+        let (pot, board) = (game.pot, game.community_cards.clone());
+        *GAME_STATE.lock().await = Some(game);
+
+        let call = PlayerAction { player_index: 0, action_type: ActionType::Call, amount: None };
+        player_action(JsonExtractor(call)).await.0.unwrap();
+        let Json(diff) = state_diff(Query(DiffQuery { since })).await;
+        let diff = diff.unwrap();
+        assert_eq!(diff.version, since + 1);
+        assert!(!diff.full);
+        assert!(diff.pot.is_some_and(|p| p > pot));
+        assert!(diff.players.contains_key(&0));
+        assert!(diff.community_cards.is_none(), "the board is still {:?}", board);
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert!(json.get("deck").is_none());
+        assert!(json.get("community_cards").is_none());
+        assert!(json.get("pot").is_some());
+
+        let Json(stale) = state_diff(Query(DiffQuery { since: 0 })).await;
+        let stale = stale.unwrap();
+        assert!(stale.full && stale.community_cards.is_some() && stale.players.len() == 3);
+    }
+
+    #[tokio::test]
+    async fn robots_act_first_on_the_flop_after_the_blinds() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = GameState::new(&NewGameQuery { game_mode: GameMode::RobotPlay, ..humans_only(3) }).unwrap();
+        // Dealer 0, small blind 1, big blind 2.
+        assert_eq!((game.dealer, game.blind_seats), (0, Some((1, 2))));
+        for (player_index, action_type) in [(0, ActionType::Call), (1, ActionType::Call), (2, ActionType::Check)] {
+            game.handle_action(Action { player_index, action_type, amount: None }).unwrap();
+        }
+        game.players[1].is_robot = true;
+        game.players[2].is_robot = true;
+        *GAME_STATE.lock().await = Some(game);
+
+        // Seat 1 is first to act on the flop; the robots play before handing the turn over.
+        let Json(game) = deal_flop().await;
+        assert_eq!(game.community_cards.len(), 3);
+        assert_eq!(game.current_player, 0);
+        let action_type = if game.current_bet > 0 { ActionType::Call } else { ActionType::Check };
+        let human = PlayerAction { player_index: 0, action_type, amount: None };
+        assert!(player_action(JsonExtractor(human)).await.0.is_ok());
+    }
+
+    #[tokio::test]
+    async fn player_action_rejects_acting_out_of_turn() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = three_handed_after_blinds();
+        game.players[0].is_robot = true;
+        let pot = game.pot;
+        *GAME_STATE.lock().await = Some(game);
+
+        let call = PlayerAction { player_index: 2, action_type: ActionType::Call, amount: None };
+        let err = player_action(JsonExtractor(call)).await.0.unwrap_err();
+        assert!(matches!(err, GameError::OutOfTurn { .. }), "{}", err);
+        {
+            let state = GAME_STATE.lock().await;
+            let game = state.as_ref().unwrap();
+            assert_eq!((game.current_player, game.pot), (0, pot));
+            assert!(game.hand_history.last().unwrap().actions.is_empty());
+        }
+
+        // Once the action comes round, the big blind may take their option.
+        {
+            let mut state = GAME_STATE.lock().await;
+            let game = state.as_mut().unwrap();
+            for seat in [0, 1] {
+                game.handle_action(Action { player_index: seat, action_type: ActionType::Call, amount: None }).unwrap();
+            }
+        }
+        let check = PlayerAction { player_index: 2, action_type: ActionType::Check, amount: None };
+        assert!(player_action(JsonExtractor(check)).await.0.is_ok());
+    }
+
+    #[tokio::test]
+    async fn errors_serialize_with_a_code_and_message() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = three_handed_after_blinds();
+        let err = game.handle_action(Action { player_index: 0, action_type: ActionType::Check, amount: None }).unwrap_err();
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({ "code": "illegal_check", "message": "Cannot check when there's a bet" })
+        );
+
+        let raise = Action { player_index: 0, action_type: ActionType::Raise, amount: Some(15) };
+        let err = serde_json::to_value(game.handle_action(raise).unwrap_err()).unwrap();
+        assert_eq!(err["code"], "raise_too_small");
+        assert_eq!(err["message"], "Raise to 15 is below the minimum of 20");
+
+        *GAME_STATE.lock().await = None;
+        let response = serde_json::to_value(legal_actions().await.0).unwrap();
+        assert_eq!(response["Err"]["code"], "no_active_game");
+        assert_eq!(response["Err"]["message"], "No active game");
+    }
+
+    #[tokio::test]
+    async fn player_action_takes_any_human_seat() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let config = NewGameQuery { num_players: 4, game_mode: GameMode::RobotPlay, human_count: 2, ..Default::default() };
+        let game = GameState::new(&config).unwrap();
+        let robots: Vec<bool> = game.players.iter().map(|p| p.is_robot).collect();
+        assert_eq!(robots, [false, false, true, true]);
+        assert_eq!((game.players[0].name.as_str(), game.players[1].name.as_str()), ("Player 1", "Player 2"));
+        *GAME_STATE.lock().await = Some(game);
+
+        // The robot after the big blind has already acted, and both humans face at least the blind.
+        let call = |player_index| PlayerAction { player_index, action_type: ActionType::Call, amount: None };
+        let err = player_action(JsonExtractor(call(2))).await.0.unwrap_err();
+        assert_eq!(err, GameError::NotHumanSeat);
+        assert_eq!(player_action(JsonExtractor(call(7))).await.0.unwrap_err(), GameError::NotHumanSeat);
+        assert!(matches!(player_action(JsonExtractor(call(1))).await.0, Err(GameError::OutOfTurn { .. })));
+
+        player_action(JsonExtractor(call(0))).await.0.unwrap();
+        let view = player_action(JsonExtractor(call(1))).await.0.unwrap();
+        assert_eq!(view.players[1].cards.len(), 2, "the acting player sees their own cards");
+        assert!(view.players[0].cards.is_empty());
+
+        assert!(GameState::new(&NewGameQuery { human_count: 0, ..Default::default() }).is_err());
+        assert!(GameState::new(&NewGameQuery { human_count: 3, ..Default::default() }).is_err());
+    }
+
+    #[tokio::test]
+    async fn player_stats_returns_one_seat() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = test_game(3);
+        game.stats.players[2].games_won = 4;
+        *GAME_STATE.lock().await = Some(game);
+
+        let Json(stats) = player_stats(Path(2)).await.unwrap();
+        assert_eq!(stats.games_won, 4);
+        let (status, Json(err)) = player_stats(Path(3)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(err, GameError::NoSuchSeat);
+    }
+
+    #[tokio::test]
+    async fn new_game_accepts_a_json_body() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let body = serde_json::json!({
+            "num_players": 4,
+            "game_mode": "RobotPlay",
+            "starting_chips": 2500,
+            "big_blind": 50,
+            "small_blind": 25,
+            "straddle": true,
+        });
+        let Json(game) = new_game_json(JsonExtractor(serde_json::from_value(body).unwrap())).await.unwrap();
+        assert_eq!(game.players.len(), 4);
+        assert!(game.players.iter().all(|p| p.chips + p.current_bet == 2500), "only the blinds and straddle are in");
+        assert_eq!((game.small_blind, game.big_blind), (25, 50));
+        assert!(game.straddle);
+        assert_eq!(GAME_STATE.lock().await.as_ref().unwrap().game_mode, GameMode::RobotPlay);
+
+        let invalid = serde_json::from_value(serde_json::json!({
+            "num_players": 9,
+            "game_mode": "Simulation",
+            "starting_chips": 1000,
+        }));
+        let (status, _) = new_game_json(JsonExtractor(invalid.unwrap())).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn standard_deck_holds_every_card_once() {
+        let deck = standard_deck();
+        assert_eq!(deck.len(), 52);
+        let unique: std::collections::HashSet<Card> = deck.iter().copied().collect();
+        assert_eq!(unique.len(), 52);
+        for suit in [Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades] {
+            assert_eq!(deck.iter().filter(|c| c.suit == suit).count(), 13);
+        }
+        for value in 2..=14 {
+            assert_eq!(deck.iter().filter(|c| c.value() == value).count(), 4);
+        }
+
+        let mut shuffled = shuffled_deck(Variant::Holdem, &mut rand::thread_rng());
+        shuffled.sort_by_key(|c| deck.iter().position(|d| d == c));
+        assert_eq!(shuffled, deck);
+    }
+
+    #[test]
+    fn dealing_more_cards_than_the_deck_holds_is_an_error() {
+        let mut deck = shuffled_deck(Variant::Holdem, &mut rand::thread_rng());
+        let err = deal_hole_cards(&mut deck, 12, Variant::Omaha).unwrap_err();
+        assert!(err.contains("needs 53 cards"), "{}", err);
+        assert_eq!(deck.len(), 52, "a failed deal leaves the deck alone");
+        // Five-card draw has no board to save cards for.
+        assert_eq!(deal_hole_cards(&mut deck, 10, Variant::FiveCardDraw).unwrap().len(), 10);
+
+        // Pad a game with extra seats until a new hand can't be dealt.
+        let mut game = test_game(8);
+        let extra = game.players[1].clone();
+        game.players.extend(std::iter::repeat_n(extra, 16));
+        let before = game.players[0].cards.clone();
+        assert!(game.deal_new_hand().is_err());
+        assert_eq!(game.players[0].cards, before);
+    }
+
+    #[test]
+    fn expired_deadline_auto_acts_for_the_human() {
+        let mut game = GameState::new(&NewGameQuery {
+            num_players: 2,
+            game_mode: GameMode::RobotPlay,
+            action_timeout_secs: Some(30),
+            ..Default::default()
+        })
+        .unwrap();
+        let deadline = game.action_deadline.expect("the human is up with a timeout configured");
+        assert!(deadline > chrono::Utc::now());
+        assert_eq!(game.enforce_action_deadline(chrono::Utc::now()).unwrap().map(|a| a.action_type), None);
+
+        // Once the small blind calls the big blind owes nothing, so timing out checks rather than folds.
+        game.players[1].is_robot = false;
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        game.action_deadline = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+        let action = game.enforce_action_deadline(chrono::Utc::now()).unwrap().unwrap();
+        assert_eq!((action.player_index, action.action_type), (1, ActionType::Check));
+        let last = &game.hand_history.last().unwrap().actions[1].action;
+        assert_eq!((last.player_index, last.action_type.clone()), (1, ActionType::Check));
+    }
+
+    #[test]
+    fn expired_deadline_folds_when_facing_a_bet() {
+        let mut game = GameState::new(&NewGameQuery {
+            num_players: 2,
+            game_mode: GameMode::RobotPlay,
+            action_timeout_secs: Some(30),
+            ..Default::default()
+        })
+        .unwrap();
+        // The small blind still owes the rest of the big blind.
+        game.action_deadline = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+        let action = game.enforce_action_deadline(chrono::Utc::now()).unwrap().unwrap();
+        assert_eq!(action.action_type, ActionType::Fold);
+        assert!(game.players[0].cards.is_empty());
+    }
+
+    #[test]
+    fn draining_the_time_bank_folds_the_player() {
+        let mut game = GameState::new(&NewGameQuery {
+            num_players: 2,
+            game_mode: GameMode::RobotPlay,
+            action_timeout_secs: Some(30),
+            time_bank_secs: 10,
+            ..Default::default()
+        })
+        .unwrap();
+        let now = chrono::Utc::now();
+
+        // Four seconds over the clock come out of the bank rather than costing the hand.
+        game.action_deadline = Some(now - chrono::Duration::seconds(4));
+        assert_eq!(game.enforce_action_deadline(now).unwrap().map(|a| a.action_type), None);
+        assert_eq!(game.players[0].time_bank_secs, 6);
+        assert_eq!(game.action_deadline, Some(now));
+
+        // Once the bank runs dry the player folds, even though checking would be free.
+        let later = now + chrono::Duration::seconds(10);
+        let action = game.enforce_action_deadline(later).unwrap().unwrap();
+        assert_eq!((action.player_index, action.action_type), (0, ActionType::Fold));
+        assert_eq!(game.players[0].time_bank_secs, 0);
+        assert!(game.players[0].cards.is_empty());
+
+        game.deal_new_hand().unwrap();
+        assert_eq!(game.players[0].time_bank_secs, TIME_BANK_REFILL_SECS);
+        assert_eq!(game.players[1].time_bank_secs, 10);
+    }
+
+    #[test]
+    fn robot_bet_sizes_are_clamped_to_legal_amounts() {
+        let game = test_game(2);
+        let legal = game.legal_actions(1).unwrap();
+        assert_eq!(robot_bet_size(&legal, 0), None);
+        assert_eq!(robot_bet_size(&legal, 3), Some(2 * game.big_blind), "a raise at least doubles the big blind");
+        assert_eq!(robot_bet_size(&legal, 250), Some(250));
+        assert_eq!(robot_bet_size(&legal, 5000), Some(1000));
+
+        // A short stack facing a raise is raised all-in rather than beyond its chips.
+        let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 40]), ..Default::default() }).unwrap();
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(20) }).unwrap();
+        let legal = game.legal_actions(1).unwrap();
+        assert_eq!(robot_bet_size(&legal, 44), Some(40));
+    }
+
+    #[test]
+    fn robot_checks_into_an_empty_pot() {
+        for _ in 0..5 {
+            // Without blinds the pot is still empty when the human checks to the robot.
+            let mut game = GameState::new(&NewGameQuery { small_blind: 0, big_blind: 0, ..Default::default() }).unwrap();
+            game.handle_action(Action { player_index: 0, action_type: ActionType::Check, amount: None }).unwrap();
+            game.handle_robot_action().unwrap();
+            let robot_action = &game.hand_history[0].actions[1].action;
+            assert_eq!((robot_action.player_index, robot_action.action_type.clone()), (1, ActionType::Check));
+        }
+    }
+
+    #[test]
+    fn robot_oversized_raise_becomes_all_in() {
+        // The robot holds aces in the big blind and faces a raise to 20 with a stack too short
+        // for its preferred raise.
+        let mut spot = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 40]), ..Default::default() }).unwrap();
+        rig_hand(&mut spot, &[cards("7c 2d"), cards("Ah As")], &[]);
+        spot.deck = deck_without(&cards("7c 2d Ah As"));
+        spot.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(20) }).unwrap();
+
+        let mut raised = false;
+        for _ in 0..40 {
+            let mut game = spot.clone();
+            game.handle_robot_action().unwrap();
+            let robot_action = &game.hand_history[0].actions[1].action;
+            if robot_action.action_type == ActionType::Raise {
+                assert_eq!(robot_action.amount, Some(40));
+                assert_eq!(game.players[1].chips, 0);
+                raised = true;
+            }
+        }
+        assert!(raised, "the robot never raised in 40 tries");
+    }
+
+    #[test]
+    fn folded_robot_is_skipped_for_the_next_active_player() {
+        let mut game = GameState::new(&humans_only(3)).unwrap();
+        game.players[1].is_robot = true;
+        let act = |player_index, action_type| Action { player_index, action_type, amount: None };
+        for action in [act(0, ActionType::Call), act(1, ActionType::Fold), act(2, ActionType::Check)] {
+            game.handle_action(action).unwrap();
+        }
+
+        // The robot in the small blind would be first to act on the flop, but it has folded.
+        game.deal_flop();
+        assert_eq!(game.current_player, 2);
+        game.handle_robot_action().unwrap();
+        game.handle_action(act(2, ActionType::Check)).unwrap();
+        assert_eq!(game.current_player, 0);
+        assert_eq!(game.hand_history[0].actions.len(), 4);
+    }
+
+    #[test]
+    fn all_in_robot_does_not_act() {
+        let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 300, 1000]), ..humans_only(3) }).unwrap();
+        game.players[1].is_robot = true;
+        let act = |player_index, action_type, amount| Action { player_index, action_type, amount };
+        for action in [act(0, ActionType::Raise, Some(300)), act(1, ActionType::Call, None), act(2, ActionType::Call, None)] {
+            game.handle_action(action).unwrap();
+        }
+        assert!(game.players[1].is_all_in());
+
+        // The robot in the small blind would be first to act on the flop, but it is all-in.
+        game.deal_flop();
+        assert_eq!(game.current_player, 2);
+        game.handle_robot_action().unwrap();
+        game.handle_action(act(2, ActionType::Check, None)).unwrap();
+        assert_eq!(game.current_player, 0);
+        assert_eq!(game.hand_history[0].actions.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn leaderboard_ranks_players_by_profit() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = test_game(3);
+        game.stats.players[0].total_profit = -50;
+        game.stats.players[1].total_profit = 120;
+        game.stats.players[1].games_won = 3;
+        game.stats.players[1].games_played = 4;
+        game.stats.players[2].games_played = 0;
+        *GAME_STATE.lock().await = Some(game);
+
+        let board = leaderboard().await.0.unwrap();
+        let profits: Vec<i32> = board.iter().map(|e| e.total_profit).collect();
+        assert_eq!(profits, [120, 0, -50]);
+        assert_eq!(board[0].win_rate, 0.75);
+        assert_eq!(board[1].games_played, 0);
+        assert_eq!(board[1].win_rate, 0.0);
+    }
+
+    #[test]
+    fn chips_are_conserved_through_a_full_hand() {
+        let mut game = GameState::new(&humans_only(3)).unwrap();
+        let expected = 3 * 1000;
+        let check = |game: &GameState| assert_eq!(game.total_chips(), expected);
+        let act = |game: &mut GameState, action_type, amount| {
+            let player_index = game.current_player;
+            game.handle_action(Action { player_index, action_type, amount }).unwrap();
+        };
+
+        check(&game);
+        act(&mut game, ActionType::Raise, Some(40));
+        check(&game);
+        act(&mut game, ActionType::Call, None);
+        act(&mut game, ActionType::Fold, None);
+        check(&game);
+        for street in [GameState::deal_flop, GameState::deal_turn, GameState::deal_river] {
+            street(&mut game);
+            check(&game);
+            act(&mut game, ActionType::Bet, Some(25));
+            act(&mut game, ActionType::Call, None);
+            check(&game);
+        }
+        game.showdown().unwrap();
+        assert_eq!(game.pot, 0);
+        check(&game);
+    }
+
+    fn simulate_request(hole_cards: &str, opponents: usize, board: &str) -> SimulateRequest {
+        let strings = |notation: &str| notation.split_whitespace().map(String::from).collect();
+        SimulateRequest {
+            hole_cards: strings(hole_cards),
+            opponents,
+            board: strings(board),
+            dead_cards: Vec::new(),
+            sims: 2000,
+            opponent_range: None,
+        }
+    }
+
+    #[test]
+    fn opponents_who_may_fold_raise_the_chance_of_winning_the_hand() {
+        let hero = cards("7c 2d");
+        let opponents = [cards("Ah As"), cards("Kh Ks")];
+        let deck = deck_without(&cards("7c 2d Ah As Kh Ks"));
+        let stay = |p| {
+            simulate_win_probability(&hero, &opponents, Some(&[p, p]), &[], &deck, &[], 4000, Variant::Holdem)
+        };
+        let to_showdown = stay(1.0);
+        let likely_folds = stay(0.1);
+        assert!(to_showdown < 0.2, "7-2 against two big pairs: {}", to_showdown);
+        // Both opponents fold 81% of the time, leaving the pot uncontested.
+        assert!(likely_folds > 0.8, "with folds: {}", likely_folds);
+        assert_eq!(stay(0.0), 1.0);
+    }
+
+    #[test]
+    fn dead_cards_are_never_drawn() {
+        // A nut flush draw against queens: nine hearts and six overcards to come.
+        let hero = cards("Ah Kh");
+        let villain = cards("Qs Qd");
+        let board = cards("2h 7h 3c");
+        let deck = deck_without(&[hero.clone(), villain.clone(), board.clone()].concat());
+        let opponents = std::slice::from_ref(&villain);
+        let live = simulate_win_probability(&hero, opponents, None, &board, &deck, &[], 4000, Variant::Holdem);
+        // With every remaining heart known to be gone, only the overcards are left.
+        let hearts: Vec<Card> = deck.iter().filter(|c| c.suit == Suit::Hearts).copied().collect();
+        let drawn_dead = simulate_win_probability(&hero, opponents, None, &board, &deck, &hearts, 4000, Variant::Holdem);
+        assert!(live > 0.45, "flush draw equity was {}", live);
+        assert!(drawn_dead < live - 0.2, "{} vs {}", drawn_dead, live);
+
+        let mut request = simulate_request("Ah Kh", 1, "2h 7h 3c");
+        let (hidden_live, _) = simulate_spot(&request).unwrap();
+        request.dead_cards = hearts.iter().map(|c| c.to_string()).collect();
+        assert!(simulate_spot(&request).unwrap().0 < hidden_live - 0.1);
+        request.dead_cards.push("Ah".to_string());
+        assert_eq!(simulate_spot(&request), Err("Duplicate card Ah".to_string()));
+    }
+
+    #[tokio::test]
+    async fn a_pot_sized_bluff_needs_folds_half_the_time() {
+        let request = FoldEquityRequest { bet: 100, pot: 100, hole_cards: Vec::new(), board: Vec::new() };
+        let response = fold_equity(JsonExtractor(request)).await.0.unwrap();
+        assert_eq!(response.required_fold_frequency, 0.5);
+        assert_eq!(response.equity_when_called, None);
+
+        // A half-pot bluff needs a third; a flush draw still has plenty of equity when called.
+        let request = FoldEquityRequest {
+            bet: 50,
+            pot: 100,
+            hole_cards: vec!["Ah".to_string(), "Th".to_string()],
+            board: vec!["Kh".to_string(), "7h".to_string(), "2c".to_string()],
+        };
+        let response = fold_equity(JsonExtractor(request)).await.0.unwrap();
+        assert!((response.required_fold_frequency - 1.0 / 3.0).abs() < 1e-9);
+        let equity = response.equity_when_called.unwrap();
+        assert!((0.45..0.75).contains(&equity), "nut flush draw equity {}", equity);
+
+        let free = FoldEquityRequest { bet: 0, pot: 100, hole_cards: Vec::new(), board: Vec::new() };
+        assert!(fold_equity(JsonExtractor(free)).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_made_straight_with_a_flush_draw_has_nine_outs() {
+        let strings = |notation: &str| notation.split_whitespace().map(String::from).collect();
+        let request = OutsRequest { hole_cards: strings("8h 9h"), board: strings("Th Jh Qc") };
+        let response = remaining_outs(JsonExtractor(request)).await.0.unwrap();
+        assert_eq!(response.hand_type, HandType::Straight);
+        // Only another heart improves it: a flush, or a straight flush with the seven or king.
+        assert_eq!(response.outs, 9);
+        assert!(response.cards.iter().all(|c| c.suit == Suit::Hearts));
+
+        let river = OutsRequest { hole_cards: strings("8h 9h"), board: strings("Th Jh Qc 2d 3s") };
+        assert!(remaining_outs(JsonExtractor(river)).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn evaluate_picks_out_the_flush() {
+        let request = EvaluateRequest {
+            cards: "Ah 9h Qd 5h 2h Jc Kh".split_whitespace().map(String::from).collect(),
+        };
+        let response = evaluate(JsonExtractor(request)).await.0.unwrap();
+        assert_eq!(response.hand_type, HandType::Flush);
+        assert_eq!(response.description, "Flush, Ace high");
+        assert_eq!(response.cards, cards("Ah 9h 5h 2h Kh"));
+
+        let too_few = EvaluateRequest { cards: vec!["Ah".to_string(); 4] };
+        assert!(evaluate(JsonExtractor(too_few)).await.0.is_err());
+        let repeated = EvaluateRequest { cards: vec!["Ah".to_string(); 5] };
+        assert!(evaluate(JsonExtractor(repeated)).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn simulate_aces_against_one_random_hand() {
+        let response = simulate(JsonExtractor(simulate_request("Ah As", 1, ""))).await.0.unwrap();
+        assert!(
+            (0.80..0.90).contains(&response.win_probability),
+            "AA preflop equity was {}",
+            response.win_probability
+        );
+    }
+
+    #[tokio::test]
+    async fn simulate_reports_the_error_of_the_deals_it_completed() {
+        // With most premium cards dead, two opponents holding only premium hands often find
+        // nothing left to deal to the second of them.
+        let mut request = simulate_request("Ah As", 2, "");
+        request.opponent_range = Some(RangePreset::Top5);
+        request.dead_cards = cards("Ks Kh Kd Qs Qh Qd Qc Js Jh Jd Ts Th Td").iter().map(|c| c.to_string()).collect();
+        let (_, completed) = simulate_spot(&request).unwrap();
+        assert!(completed < request.sims, "{} of {} deals completed", completed, request.sims);
+
+        let response = simulate(JsonExtractor(request)).await.0.unwrap();
+        let p = response.win_probability;
+        assert!(response.standard_error > standard_error(p, 2000), "{} is too tight", response.standard_error);
+    }
+
+    #[test]
+    fn simulate_rejects_bad_spots() {
+        assert_eq!(simulate_spot(&simulate_request("Ah Ah", 1, "")), Err("Duplicate card Ah".to_string()));
+        assert_eq!(simulate_spot(&simulate_request("Ah Kd", 1, "Kd 2c 3c")), Err("Duplicate card Kd".to_string()));
+        assert!(simulate_spot(&simulate_request("Ah Kx", 1, "")).is_err());
+        assert!(simulate_spot(&simulate_request("Ah Kd", 0, "")).is_err());
+        assert!(simulate_spot(&simulate_request("Ah Kd", 1, "2c 3c")).is_err());
+    }
+
+    #[test]
+    fn duplicated_cards_are_caught() {
+        let mut game = test_game(2);
+        assert_eq!(game.validate_no_duplicates(), Ok(()));
+
+        // Copy a card still in the deck into a player's hand.
+        let copied = game.deck[0];
+        game.players[1].cards[0] = copied;
+        assert_eq!(game.validate_no_duplicates(), Err(format!("Duplicate card {}", copied)));
+
+        let mut game = test_game(2);
+        game.deal_flop();
+        game.community_cards[2] = game.players[0].cards[1];
+        assert!(game.validate_no_duplicates().is_err());
+    }
+
+    /// Reads the next server-sent event from a response body and parses its JSON payload.
+    async fn next_event(body: &mut (impl Stream<Item = Result<axum::body::Bytes, axum::Error>> + Unpin)) -> GameState {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+            .await
+            .expect("no event within 5s")
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(frame.to_vec()).unwrap();
+        let data = text.trim().strip_prefix("data: ").expect("a data event");
+        serde_json::from_str(data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn spectators_receive_an_event_after_an_action() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let game = GameState::new(&humans_only(2)).unwrap();
+        *GAME_STATE.lock().await = Some(game);
+
+        let hidden = spectate(Query(SpectateQuery { reveal: false })).await.into_response();
+        let revealed = spectate(Query(SpectateQuery { reveal: true })).await.into_response();
+        let mut hidden = hidden.into_body().into_data_stream();
+        let mut revealed = revealed.into_body().into_data_stream();
+
+        let action = PlayerAction { player_index: 0, action_type: ActionType::Call, amount: None };
+        player_action(JsonExtractor(action)).await.0.unwrap();
+
+        let event = next_event(&mut hidden).await;
+        assert_eq!(event.last_action.unwrap().action_type, ActionType::Call);
+        assert!(event.players.iter().all(|p| p.cards.is_empty()));
+        let event = next_event(&mut revealed).await;
+        assert!(event.players.iter().all(|p| p.cards.len() == 2));
+    }
+
+    #[test]
+    fn opponent_hole_cards_are_redacted_until_showdown() {
+        let mut game = test_game(3);
+        let view = game.redacted_for(0);
+        assert_eq!(view.players[0].cards, game.players[0].cards);
+        assert!(view.players[1].cards.is_empty());
+        assert!(view.players[2].cards.is_empty());
+        assert!(view.deck.is_empty());
+
+        let winners = game.showdown().unwrap();
+        let view = game.redacted_for(0);
+        for (seat, player) in view.players.iter().enumerate() {
+            assert_eq!(player.cards.len() == 2, seat == 0 || winners.contains(&seat));
+        }
+    }
+
+    #[tokio::test]
+    async fn player_action_hides_robot_cards_against_robots() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let game = GameState::new(&NewGameQuery { game_mode: GameMode::RobotPlay, ..humans_only(2) }).unwrap();
+        *GAME_STATE.lock().await = Some(game);
+
+        let action = PlayerAction { player_index: 0, action_type: ActionType::Call, amount: None };
+        let view = player_action(JsonExtractor(action)).await.0.unwrap();
+        assert_eq!(view.players[0].cards.len(), 2);
+        assert!(view.players[1].cards.is_empty());
+        let full = GAME_STATE.lock().await.clone().unwrap();
+        assert_eq!(full.players[1].cards.len(), 2);
+    }
+
+    /// How often the robot in seat 1 continues with 7-2 on a board it can only chop, facing a
+    /// pot-sized bet that it should always fold to.
+    fn robot_bad_calls(difficulty: f64, trials: usize) -> usize {
+        let mut spot = test_game(2);
+        spot.difficulty = difficulty;
+        rig_hand(&mut spot, &[cards("Ah As"), cards("7c 2d")], &cards("Ad Kd Qs Jc 9h"));
+        spot.deck = deck_without(&cards("Ah As 7c 2d Ad Kd Qs Jc 9h"));
+        spot.players[0].current_bet = 20;
+        spot.current_bet = 20;
+        spot.pot = 40;
+        spot.current_player = 1;
+        (0..trials)
+            .filter(|_| {
+                let mut game = spot.clone();
+                game.handle_robot_action().unwrap();
+                game.players[1].cards.len() == 2
+            })
+            .count()
+    }
+
+    #[test]
+    fn easy_robots_make_more_losing_calls_than_hard_ones() {
+        let easy = robot_bad_calls(0.0, 60);
+        let hard = robot_bad_calls(1.0, 60);
+        assert!(easy > hard, "easy robots continued {} times, hard robots {}", easy, hard);
+        assert_eq!(hard, 0);
+    }
+
+    #[tokio::test]
+    async fn player_action_reports_specific_raise_errors() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        // The big blind, starting with `stack` chips, faces a raise to 40.
+        let facing_a_raise = |stack| {
+            let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, stack]), ..humans_only(2) }).unwrap();
+            game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(40) }).unwrap();
+            game
+        };
+        *GAME_STATE.lock().await = Some(facing_a_raise(1000));
+
+        let raise = |amount| PlayerAction { player_index: 1, action_type: ActionType::Raise, amount: Some(amount) };
+        let Json(result) = player_action(JsonExtractor(raise(50))).await;
+        assert_eq!(result.unwrap_err(), GameError::RaiseTooSmall { verb: "Raise", amount: 50, min: 70 });
+        let Json(result) = player_action(JsonExtractor(raise(1200))).await;
+        assert_eq!(result.unwrap_err(), GameError::RaiseTooLarge { verb: "Raise", amount: 1200, all_in: 1000 });
+
+        // With only 60 chips, less than a full raise, the only raise allowed is all-in.
+        *GAME_STATE.lock().await = Some(facing_a_raise(60));
+        let Json(result) = player_action(JsonExtractor(raise(50))).await;
+        assert_eq!(result.unwrap_err(), GameError::AllInOnly { verb: "Raise", amount: 50, all_in: 60 });
+        let Json(result) = player_action(JsonExtractor(raise(60))).await;
+        assert_eq!(result.unwrap().players[1].chips, 0);
+
+        // Facing a raise bigger than their stack, a player can only call for what they have.
+        *GAME_STATE.lock().await = Some(facing_a_raise(30));
+        let Json(result) = player_action(JsonExtractor(raise(30))).await;
+        assert_eq!(result.unwrap_err(), GameError::AllInCallOnly { call: 20 });
+    }
+
+    /// A four-handed table of humans with the given ante and straddle settings.
+    fn four_handed(ante: u32, straddle: bool) -> GameState {
+        GameState::new(&NewGameQuery { ante, straddle, ..humans_only(4) }).unwrap()
+    }
+
+    #[test]
+    fn antes_go_into_the_pot_without_counting_as_bets() {
+        let game = four_handed(2, false);
+        // Four 2-chip antes plus the 5 and 10 blinds.
+        assert_eq!(game.pot, 4 * 2 + 5 + 10);
+        assert_eq!(game.current_bet, 10);
+        let bets: Vec<u32> = game.players.iter().map(|p| p.current_bet).collect();
+        assert_eq!(bets.iter().sum::<u32>(), 15);
+        assert_eq!(game.total_chips(), 4000);
+    }
+
+    #[test]
+    fn straddler_acts_last_preflop() {
+        let mut game = four_handed(0, true);
+        // Dealer 0, small blind 1, big blind 2, straddle 3.
+        assert_eq!(game.dealer, 0);
+        assert_eq!(game.current_bet, 20);
+        assert_eq!(game.players[3].current_bet, 20);
+        assert_eq!(game.pot, 35);
+        assert_eq!(game.legal_actions(0).unwrap().min_raise, 40);
+
+        for _ in 0..3 {
+            let player_index = game.current_player;
+            assert_ne!(player_index, 3, "the straddler must not act before the others");
+            game.handle_action(Action { player_index, action_type: ActionType::Call, amount: None }).unwrap();
+        }
+        assert_eq!(game.current_player, 3);
+        assert!(game.legal_actions(3).unwrap().actions.contains(&ActionType::Check));
+    }
+
+    #[test]
+    fn double_straddle_doubles_the_live_bet_and_acts_last() {
+        let stacks = vec![1000, 1000, 1000, 1000, 30];
+        let mut game = GameState::new(&NewGameQuery { straddles: 2, stacks: Some(stacks), ..humans_only(5) }).unwrap();
+        // Dealer 0, small blind 1, big blind 2, straddle 3 for 20, double straddle 4 for 40.
+        assert_eq!(game.players[3].current_bet, 20);
+        assert_eq!(game.players[4].current_bet, 30, "the short double straddler is all-in");
+        assert_eq!(game.current_bet, 30);
+        assert_eq!(game.last_straddler, Some(4));
+        assert_eq!(game.pot, 5 + 10 + 20 + 30);
+
+        let mut order = Vec::new();
+        while !game.betting_round_complete() {
+            let player_index = game.current_player;
+            order.push(player_index);
+            game.handle_action(Action { player_index, action_type: ActionType::Call, amount: None }).unwrap();
+        }
+        assert_eq!(order, vec![0, 1, 2, 3], "action starts after the last straddler");
+
+        let game = GameState::new(&NewGameQuery { num_players: 5, straddles: 2, ..Default::default() }).unwrap();
+        assert_eq!(game.current_bet, 40);
+        assert_eq!(game.current_player, 0);
+        assert_eq!(game.legal_actions(0).unwrap().min_raise, 80);
+
+        let too_many = GameState::new(&NewGameQuery { num_players: 3, straddles: 2, ..Default::default() });
+        assert!(too_many.is_err());
+    }
+
+    #[tokio::test]
+    async fn probabilities_are_recomputed_on_the_current_board() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = test_game(3);
+        game.deal_flop();
+        let board = game.community_cards.clone();
+        let deck_size = game.deck.len();
+        *GAME_STATE.lock().await = Some(game);
+
+        let probabilities = recompute_probabilities(Query(ProbabilitiesQuery { sims: Some(500) })).await.0.unwrap();
+        assert_eq!(probabilities.len(), 3);
+        assert!(probabilities.iter().all(|p| (0.0..=1.0).contains(p)), "{:?}", probabilities);
+        let game = GAME_STATE.lock().await.clone().unwrap();
+        assert_eq!(game.community_cards, board, "nothing is dealt");
+        assert_eq!(game.deck.len(), deck_size);
+        let flop_entries = game.players[0].equity_history.iter().filter(|(p, _)| *p == GamePhase::Flop).count();
+        assert_eq!(flop_entries, 1);
+
+        assert!(recompute_probabilities(Query(ProbabilitiesQuery { sims: Some(0) })).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn rabbit_hunt_peeks_the_rest_of_the_board() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = GameState::new(&humans_only(2)).unwrap();
+        game.deal_flop();
+        assert!(game.rabbit_hunt().is_err(), "no peeking while the hand is in play");
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Fold, amount: None }).unwrap();
+        let deck = game.deck.clone();
+        let board = game.community_cards.clone();
+        *GAME_STATE.lock().await = Some(game);
+
+        let peeked = rabbit_hunt().await.0.unwrap();
+        assert_eq!(peeked, [deck[deck.len() - 1], deck[deck.len() - 2]]);
+        let game = GAME_STATE.lock().await.clone().unwrap();
+        assert_eq!(game.deck, deck);
+        assert_eq!(game.community_cards, board);
+    }
+
+    #[tokio::test]
+    async fn deal_street_moves_from_flop_to_turn_to_river() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        *GAME_STATE.lock().await = Some(test_game(2));
+        async fn act(player_index: usize, action_type: ActionType, amount: Option<u32>) {
+            let mut state = GAME_STATE.lock().await;
+            state.as_mut().unwrap().handle_action(Action { player_index, action_type, amount }).unwrap();
+        }
+
+        assert_eq!(deal_next_street().await.0.unwrap_err(), GameError::BettingNotComplete);
+        act(0, ActionType::Call, None).await;
+        act(1, ActionType::Check, None).await;
+        for (board, phase) in [(3, GamePhase::Flop), (4, GamePhase::Turn), (5, GamePhase::River)] {
+            let game = deal_next_street().await.0.unwrap();
+            assert_eq!(game.community_cards.len(), board);
+            assert_eq!(game.current_player, 1, "the seat left of the button acts first");
+            assert_eq!(game.hand_history.last().unwrap().streets.last().unwrap().phase, phase);
+            if phase == GamePhase::River {
+                break;
+            }
+
+            // A bet nobody has answered yet keeps the next street from being dealt.
+            act(1, ActionType::Bet, Some(20)).await;
+            assert_eq!(deal_next_street().await.0.unwrap_err(), GameError::BettingNotComplete);
+            act(0, ActionType::Call, None).await;
+        }
+        assert_eq!(deal_next_street().await.0.unwrap_err(), GameError::NoStreetToDeal);
+    }
+
+    #[test]
+    fn equity_history_records_each_street() {
+        let mut game = test_game(2);
+        game.deal_flop();
+        game.deal_turn();
+        game.deal_river();
+
+        for player in &game.players {
+            let phases: Vec<GamePhase> = player.equity_history.iter().map(|(phase, _)| phase.clone()).collect();
+            assert_eq!(phases, [GamePhase::PreFlop, GamePhase::Flop, GamePhase::Turn, GamePhase::River]);
+            assert_eq!(player.equity_history.last().unwrap().1, player.win_probability);
+        }
+
+        let seat = game.current_player;
+        game.handle_action(Action { player_index: seat, action_type: ActionType::Fold, amount: None }).unwrap();
+        game.deal_new_hand().unwrap();
+        assert!(game.players.iter().all(|p| p.equity_history.len() == 1));
+    }
+
+    #[test]
+    fn losing_hand_can_be_mucked_at_showdown() {
+        let mut game = test_game(3);
+        rig_hand(&mut game, &[cards("Ah As"), cards("7c 2d"), cards("Kh Ks")], &cards("Ad 9d 5s 4c Th"));
+        let show_or_muck = |action_type| Action { player_index: 1, action_type, amount: None };
+        assert_eq!(
+            game.handle_action(show_or_muck(ActionType::Muck)),
+            Err(GameError::NotAtShowdown)
+        );
+
+        for (player_index, action_type) in [(0, ActionType::Call), (1, ActionType::Call), (2, ActionType::Check)] {
+            game.handle_action(Action { player_index, action_type, amount: None }).unwrap();
+        }
+        assert_eq!(game.showdown().unwrap(), vec![0]);
+        assert!(game.handle_action(Action { player_index: 2, action_type: ActionType::Call, amount: None }).is_err());
+        assert!(game.legal_actions(0).unwrap().actions.is_empty(), "the winner is always shown");
+        game.handle_action(show_or_muck(ActionType::Muck)).unwrap();
+        game.handle_action(Action { player_index: 2, action_type: ActionType::Show, amount: None }).unwrap();
+
+        let hand = game.hand_history.last().unwrap();
+        assert_eq!(hand.revealed, [0, 2]);
+        let recorded: Vec<(ActionType, GamePhase)> =
+            hand.actions[3..].iter().map(|a| (a.action.action_type.clone(), a.phase.clone())).collect();
+        assert_eq!(recorded, [(ActionType::Muck, GamePhase::Showdown), (ActionType::Show, GamePhase::Showdown)]);
+
+        // Spectators and the other players never see the mucked hand.
+        let view = game.spectator_view(false);
+        assert!(view.players[1].cards.is_empty());
+        assert!(view.hand_history.last().unwrap().player_cards[1].is_empty());
+        assert_eq!(view.players[2].cards, cards("Kh Ks"));
+        let text = to_pokerstars_format(game.hand_history.last().unwrap(), 1, &game.players, game.variant, game.betting_structure);
+        assert!(text.contains("mucks hand"));
+        assert!(text.contains("shows [Kh Ks]"));
+
+        // Simulation mode shows every hand in play, but not one mucked at showdown.
+        let history = game.history_view();
+        let hand = history.last().unwrap();
+        assert!(hand.player_cards[1].is_empty());
+        assert_eq!(hand.player_cards[2], cards("Kh Ks"));
+        assert!(hand.showdown_results.iter().all(|r| r.player_index != 1));
+    }
+
+    #[test]
+    fn split_pot_showdown_results() {
+        let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 100, 1000]), ..humans_only(3) }).unwrap();
+        // Both remaining players hold an ace-high straight; the short stack is all-in.
+        rig_hand(&mut game, &[cards("Ac 2d"), cards("Ad 3c"), cards("7s 8s")], &cards("Kh Qd Js Th 4c"));
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(100) }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        game.handle_action(Action { player_index: 2, action_type: ActionType::Fold, amount: None }).unwrap();
+
+        assert_eq!(game.showdown().unwrap(), vec![0, 1]);
+        let results = &game.hand_history.last().unwrap().showdown_results;
+        assert_eq!(results.len(), 2);
+        // Each takes half of the two raises and the folded big blind.
+        for result in results {
+            assert_eq!(result.amount_won, 105);
+            assert_eq!(result.hand_type, HandType::Straight);
+            assert_eq!(result.description, "Straight, Ace high");
+            assert!(cards("Kh Qd Js Th").iter().all(|c| result.best_cards.contains(c)));
+        }
+        assert!(!results[0].all_in);
+        assert!(results[1].all_in);
+    }
+
+    #[test]
+    fn hands_are_described_like_a_dealer_would() {
+        let describe = |notation| describe_hand(&evaluate_hand(&cards(notation)));
+        assert_eq!(describe("Ah Kd 9c 7s 2h"), "Ace high");
+        assert_eq!(describe("6h 6d 9c 7s 2h"), "Pair of Sixes");
+        assert_eq!(describe("Kh Kd 2c 2s Ah"), "Two pair, Kings and Twos");
+        assert_eq!(describe("Kh Kd Kc 2s 2h"), "Full house, Kings full of Twos");
+        assert_eq!(describe("Ah 2d 3c 4s 5h"), "Straight, Five high");
+        assert_eq!(describe("Ah Kh Qh Jh Th"), "Royal flush");
+    }
+
+    #[test]
+    fn betting_round_completes_after_everyone_matches() {
+        let mut game = three_handed_after_blinds();
+        assert!(!game.betting_round_complete());
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+        assert!(!game.betting_round_complete(), "the big blind still has their option");
+        game.handle_action(Action { player_index: 2, action_type: ActionType::Raise, amount: Some(30) }).unwrap();
+        assert!(!game.betting_round_complete());
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Fold, amount: None }).unwrap();
+        assert!(game.betting_round_complete());
+    }
+
+    #[tokio::test]
+    async fn auto_play_runs_a_simulation_hand_to_showdown() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        *GAME_STATE.lock().await = Some(test_game(3));
+
+        let game = auto_play().await.0.unwrap();
+        let hand = game.hand_history.last().unwrap();
+        assert_eq!(hand.phase, GamePhase::Showdown);
+        assert!(hand.winner.is_some());
+        assert_eq!(game.pot, 0);
+        assert_eq!(game.total_chips(), 3000);
+        assert!(auto_play().await.0.is_err(), "the hand is already over");
+
+        let robot_game = GameState::new(&NewGameQuery { game_mode: GameMode::RobotPlay, ..Default::default() });
+        assert!(robot_game.unwrap().auto_play().is_err());
+    }
+
+    /// The original recursive evaluator, kept to check the in-place one against.
+    fn reference_best_hand(cards: &[Card]) -> Hand {
+        fn recursive_combinations(items: &[Card], k: usize) -> Vec<Vec<Card>> {
+            if k == 0 {
+                return vec![vec![]];
+            }
+            let mut result = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                for mut combo in recursive_combinations(&items[i + 1..], k - 1) {
+                    combo.insert(0, *item);
+                    result.push(combo);
+                }
+            }
+            result
+        }
+        recursive_combinations(cards, 5).iter().map(|combo| evaluate_hand(combo)).max().unwrap()
+    }
+
+    #[test]
+    fn best_hand_matches_the_recursive_evaluator() {
+        for _ in 0..500 {
+            let seven = new_shuffled_deck()[..7].to_vec();
+            assert_eq!(evaluate_best_hand(&seven).unwrap(), reference_best_hand(&seven), "{}", format_cards(&seven));
+        }
+        assert_eq!(combinations(&[1, 2, 3, 4], 2).len(), 6);
+        assert_eq!(combinations(&[1, 2, 3], 3), vec![vec![1, 2, 3]]);
+        assert_eq!(combinations(&[1, 2], 0), vec![Vec::<i32>::new()]);
+        assert!(combinations(&[1, 2], 3).is_empty());
+    }
+
+    #[test]
+    fn best_hand_cards_make_the_claimed_hand() {
+        for _ in 0..500 {
+            let seven = new_shuffled_deck()[..7].to_vec();
+            let (hand, five) = best_hand_cards(&seven).unwrap();
+            assert_eq!(five.len(), 5);
+            assert!(five.iter().all(|c| seven.contains(c)));
+            check_no_duplicate_cards(&five).unwrap();
+            assert_eq!(evaluate_hand(&five), hand, "{}", format_cards(&seven));
+            assert_eq!(hand, evaluate_best_hand(&seven).unwrap());
+        }
+
+        let (hole, board) = (cards("Ah Kh 2c 3d"), cards("Qh Jh Th 9s 8s"));
+        let (hand, five) = Variant::Omaha.best_hand_cards(&hole, &board).unwrap();
+        assert_eq!(hand, Variant::Omaha.best_hand(&hole, &board).unwrap());
+        assert_eq!(evaluate_hand(&five), hand);
+        assert_eq!(hand.hand_type, HandType::RoyalFlush);
+    }
+
+    /// Counts heap allocations made on the current thread, so tests can compare allocation-heavy
+    /// code paths without interference from tests running in parallel.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    fn allocations_during(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(|count| count.get());
+        f();
+        ALLOCATIONS.with(|count| count.get()) - before
+    }
+
+    #[test]
+    fn best_hand_allocates_less_than_the_recursive_evaluator() {
+        let seven = cards("Ah Kd Qs Jc 9h 9d 2c");
+        let in_place = allocations_during(|| {
+            evaluate_best_hand(&seven).unwrap();
+        });
+        let recursive = allocations_during(|| {
+            reference_best_hand(&seven);
+        });
+        assert!(in_place < recursive, "in place: {}, recursive: {}", in_place, recursive);
+    }
+
+    /// Rough timing of the evaluator's hot path. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_best_hand() {
+        let hands: Vec<Vec<Card>> = (0..20_000).map(|_| new_shuffled_deck()[..7].to_vec()).collect();
+        for (name, evaluate) in [
+            ("in place", (|cards: &[Card]| evaluate_best_hand(cards).unwrap()) as fn(&[Card]) -> Hand),
+            ("recursive", reference_best_hand),
+        ] {
+            let mut allocations = 0;
+            let start = std::time::Instant::now();
+            for hand in &hands {
+                allocations += allocations_during(|| {
+                    std::hint::black_box(evaluate(hand));
+                });
+            }
+            println!("{}: {:?}, {} allocations", name, start.elapsed(), allocations);
+        }
+    }
+
+    /// The original HashMap-based evaluator, kept to check the counting-array one against.
+    fn reference_evaluate_hand(cards: &[Card]) -> Hand {
+        let mut values: Vec<u8> = cards.iter().map(|c| c.value()).collect();
+        values.sort_unstable_by(|a, b| b.cmp(a));
+
+        // Check flush (all cards have the same suit)
+        let is_flush = cards.iter().all(|c| c.suit == cards[0].suit);
+
+        // Check straight (sequential values)
+        let mut is_straight = false;
+        if values.windows(2).all(|w| w[0] == w[1] + 1) {
+            is_straight = true;
+        } else if values == vec![14, 5, 4, 3, 2] {
+            // Special case for Ace-low straight
+            is_straight = true;
+            values = vec![5, 4, 3, 2, 1];
+        }
+
+        // Count frequencies of card values
+        let mut freq = std::collections::HashMap::new();
+        for &v in &values {
+            *freq.entry(v).or_insert(0) += 1;
+        }
+        let mut freq_vec: Vec<_> = freq.into_iter().collect();
+        freq_vec.sort_by_key(|&(v, count)| (-count, -(v as i32)));
+
+        // Order the tie-break values by group size, then rank: trips before the pair in a full house,
+        // both pairs before the kicker in two pair, and so on.
+        let values: Vec<u8> = freq_vec
+            .iter()
+            .flat_map(|&(v, count)| std::iter::repeat_n(v, count as usize))
+            .collect();
+
+        let hand_type = if is_flush && is_straight && values[0] == 14 {
+            HandType::RoyalFlush
+        } else if is_flush && is_straight {
+            HandType::StraightFlush
+        } else if freq_vec[0].1 == 4 {
+            HandType::FourOfAKind
+        } else if freq_vec[0].1 == 3 && freq_vec.get(1).map_or(0, |&(_, c)| c) == 2 {
+            HandType::FullHouse
+        } else if is_flush {
+            HandType::Flush
+        } else if is_straight {
+            HandType::Straight
+        } else if freq_vec[0].1 == 3 {
+            HandType::ThreeOfAKind
+        } else if freq_vec[0].1 == 2 && freq_vec.get(1).map_or(0, |&(_, c)| c) == 2 {
+            HandType::TwoPair
+        } else if freq_vec[0].1 == 2 {
+            HandType::Pair
+        } else {
+            HandType::HighCard
+        };
+
+        Hand::new(hand_type, values)
+    }
+
+    #[test]
+    fn evaluate_hand_matches_the_hash_map_evaluator() {
+        for _ in 0..2000 {
+            let five = new_shuffled_deck()[..5].to_vec();
+            assert_eq!(evaluate_hand(&five), reference_evaluate_hand(&five), "{}", format_cards(&five));
+        }
+        for notation in ["Ah 2d 3c 4s 5h", "Kh Kd Kc 2s 2h", "9h 9d 9c 9s 2h", "Qh Qd 3c 3s Ah"] {
+            assert_eq!(evaluate_hand(&cards(notation)), reference_evaluate_hand(&cards(notation)));
+        }
+    }
+
+    /// The sort-and-compare evaluator the bitmask one replaced.
+    fn sorting_evaluate_hand(cards: &[Card]) -> Hand {
+        let mut values: Vec<u8> = cards.iter().map(|c| c.value()).collect();
+        values.sort_unstable_by(|a, b| b.cmp(a));
+
+        // Check flush (all cards have the same suit)
+        let is_flush = cards.iter().all(|c| c.suit == cards[0].suit);
+
+        // Check straight (sequential values)
+        let mut is_straight = false;
+        if values.windows(2).all(|w| w[0] == w[1] + 1) {
+            is_straight = true;
+        } else if values == vec![14, 5, 4, 3, 2] {
+            // Special case for Ace-low straight
+            is_straight = true;
+            values = vec![5, 4, 3, 2, 1];
+        }
+
+        // Count frequencies of card values (1 is the ace in an ace-low straight)
+        let mut counts = [0u8; 15];
+        for &v in &values {
+            counts[v as usize] += 1;
+        }
+        let mut groups = [(0u8, 0u8); 5];
+        let mut num_groups = 0;
+        for v in (1..=14u8).rev() {
+            if counts[v as usize] > 0 {
+                groups[num_groups] = (v, counts[v as usize]);
+                num_groups += 1;
+            }
+        }
+        let groups = &mut groups[..num_groups];
+        groups.sort_unstable_by_key(|&(v, count)| (std::cmp::Reverse(count), std::cmp::Reverse(v)));
+        let group_size = |i: usize| groups.get(i).map_or(0, |&(_, count)| count);
+
+        // Order the tie-break values by group size, then rank: trips before the pair in a full house,
+        // both pairs before the kicker in two pair, and so on.
+        let values: Vec<u8> = groups
+            .iter()
+            .flat_map(|&(v, count)| std::iter::repeat_n(v, count as usize))
+            .collect();
+
+        let hand_type = if is_flush && is_straight && values[0] == 14 {
+            HandType::RoyalFlush
+        } else if is_flush && is_straight {
+            HandType::StraightFlush
+        } else if group_size(0) == 4 {
+            HandType::FourOfAKind
+        } else if group_size(0) == 3 && group_size(1) == 2 {
+            HandType::FullHouse
+        } else if is_flush {
+            HandType::Flush
+        } else if is_straight {
+            HandType::Straight
+        } else if group_size(0) == 3 {
+            HandType::ThreeOfAKind
+        } else if group_size(0) == 2 && group_size(1) == 2 {
+            HandType::TwoPair
+        } else if group_size(0) == 2 {
+            HandType::Pair
+        } else {
+            HandType::HighCard
+        };
+
+        Hand::new(hand_type, values)
+    }
+
+    #[test]
+    fn bitmask_evaluator_matches_the_sorting_evaluator() {
+        for _ in 0..20_000 {
+            let five = new_shuffled_deck()[..5].to_vec();
+            assert_eq!(evaluate_hand(&five), sorting_evaluate_hand(&five), "{}", format_cards(&five));
+        }
+        // Every straight and straight flush, wheel included.
+        let ranks = "A23456789TJQKA".as_bytes();
+        for window in ranks.windows(5) {
+            for suits in ["hhhhh", "hdhhh"] {
+                let notation: Vec<String> = window
+                    .iter()
+                    .zip(suits.chars())
+                    .map(|(&rank, suit)| format!("{}{}", rank as char, suit))
+                    .collect();
+                let five = cards(&notation.join(" "));
+                assert_eq!(evaluate_hand(&five), sorting_evaluate_hand(&five), "{}", format_cards(&five));
+            }
+        }
+    }
+
+    /// Rough timing of five-card evaluation in the simulation hot path. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_evaluate_hand() {
+        let hands: Vec<Vec<Card>> = (0..200_000).map(|_| new_shuffled_deck()[..5].to_vec()).collect();
+        for (name, evaluate) in [
+            ("bitmask", evaluate_hand as fn(&[Card]) -> Hand),
+            ("sorting", sorting_evaluate_hand),
+            ("hash map", reference_evaluate_hand),
+        ] {
+            let mut allocations = 0;
+            let start = std::time::Instant::now();
+            for hand in &hands {
+                allocations += allocations_during(|| {
+                    std::hint::black_box(evaluate(hand));
+                });
+            }
+            println!("{}: {:?}, {} allocations", name, start.elapsed(), allocations);
+        }
+    }
+
+    #[tokio::test]
+    async fn human_view_hides_equity_unless_probabilities_are_shown() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        for game_mode in [GameMode::RobotPlay, GameMode::Simulation] {
+            let config = NewGameQuery { num_players: 3, game_mode, show_probabilities: false, ..Default::default() };
+            let game = GameState::new(&config).unwrap();
+            assert!(game.players.iter().any(|p| p.win_probability > 0.0), "robots still get equities");
+
+            let view = game.human_view();
+            assert!(view.players.iter().all(|p| p.win_probability == 0.0 && p.equity_history.is_empty()));
+            let json = serde_json::to_value(&view).unwrap();
+            assert!(json["players"].as_array().unwrap().iter().all(|p| p["win_probability"] == 0.0));
+
+            *GAME_STATE.lock().await = Some(game);
+            let shown = reveal_probabilities(Query(RevealProbabilitiesQuery { show: true })).await.0.unwrap();
+            assert!(shown.players.iter().any(|p| p.win_probability > 0.0));
+            assert!(shown.players.iter().all(|p| !p.equity_history.is_empty()));
+        }
+    }
+
+    #[test]
+    fn hand_history_snapshots_each_street() {
+        let mut game = test_game(3);
+        let blinds = game.small_blind + game.big_blind;
+        game.deal_flop();
+        game.deal_turn();
+        game.deal_river();
+
+        let streets = &game.hand_history.last().unwrap().streets;
+        let phases: Vec<GamePhase> = streets.iter().map(|s| s.phase.clone()).collect();
+        assert_eq!(phases, [GamePhase::PreFlop, GamePhase::Flop, GamePhase::Turn, GamePhase::River]);
+        let board_sizes: Vec<usize> = streets.iter().map(|s| s.community_cards.len()).collect();
+        assert_eq!(board_sizes, [0, 3, 4, 5]);
+        assert_eq!(streets[3].community_cards, game.community_cards);
+        assert_eq!(streets[1].community_cards[..], game.community_cards[..3]);
+        assert!(streets[0].pot >= blinds);
+        assert!(streets.windows(2).all(|w| w[0].pot <= w[1].pot));
+    }
+
+    #[test]
+    fn robots_play_with_the_personality_they_were_seated_with() {
+        let mut game = test_game(3);
+        let bluff_master = game.players[2].personality.clone();
+        assert_eq!(bluff_master.as_ref().unwrap().style, "Deceptive");
+        game.players[1].personality = bluff_master;
+        assert_eq!(game.get_robot_personality(1).style, "Deceptive");
+        assert_eq!(game.get_robot_personality(0).name, "PokerBot 3000", "an auto-played human borrows a robot's");
+    }
+
+    #[test]
+    fn bluff_master_smirks_when_raising_weak() {
+        let game = test_game(3);
+        let bluff_master = game.get_robot_personality(2);
+        assert_eq!(bluff_master.style, "Deceptive");
+        let tell = robot_tell(&bluff_master, &ActionType::Raise, false, 7).unwrap();
+        assert!(["smirks confidently", "stares you down", "stacks chips with a flourish"].contains(&tell.as_str()));
+        assert_eq!(robot_tell(&bluff_master, &ActionType::Raise, false, 7), Some(tell));
+        assert_ne!(robot_tell(&bluff_master, &ActionType::Raise, true, 7).as_deref(), Some("smirks confidently"));
+        assert_eq!(robot_tell(&bluff_master, &ActionType::Muck, false, 7), None);
+    }
+
+    #[test]
+    fn robot_actions_record_a_tell() {
+        let mut game = test_game(2);
+        if game.players[game.current_player].is_robot {
+            game.handle_robot_action().unwrap();
+        } else {
+            let seat = game.current_player;
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Call, amount: None }).unwrap();
+            game.handle_robot_action().unwrap();
+        }
+        let hand = game.hand_history.last().unwrap();
+        let robot_entry = hand.actions.iter().find(|a| game.players[a.action.player_index].is_robot).unwrap();
+        assert!(robot_entry.tell.as_ref().is_some_and(|tell| !tell.is_empty()));
+        assert!(hand.actions.iter().filter(|a| !game.players[a.action.player_index].is_robot).all(|a| a.tell.is_none()));
+    }
+
+    #[test]
+    fn push_fold_shoves_only_when_it_beats_folding() {
+        assert_eq!(push_fold(50, 20, 0.85), PushFold::Shove);
+        assert_eq!(push_fold(50, 20, 0.35), PushFold::Fold);
+        // Break-even: 0.4 * 75 - 0.6 * 50 = 0.
+        assert!(shove_ev(50, 25, 0.4).abs() < 1e-9);
+        // With dead money in a big enough pot, even a weak hand should shove.
+        assert_eq!(push_fold(20, 100, 0.3), PushFold::Shove);
+    }
+
+    #[test]
+    fn short_stacked_robots_play_push_fold() {
+        let decision = |hole: &str| {
+            let mut game = GameState::new(&NewGameQuery { stacks: Some(vec![1000, 50]), ..Default::default() }).unwrap();
+            let (human, robot) = (cards("Kd Qc"), cards(hole));
+            rig_hand(&mut game, &[human.clone(), robot.clone()], &[]);
+            game.deck = deck_without(&[human, robot].concat());
+            game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(20) }).unwrap();
+            game.robot_decision(1).unwrap()
+        };
+
+        let aces = decision("Ah As");
+        assert_eq!(aces.action_type, ActionType::Raise);
+        assert_eq!(aces.amount, Some(50), "aces shove all-in");
+        assert_eq!(decision("7h 2c").action_type, ActionType::Fold);
+    }
+
+    #[test]
+    fn robots_can_be_stepped_one_action_at_a_time() {
+        let config = NewGameQuery { num_players: 4, game_mode: GameMode::RobotPlay, ..Default::default() };
+        let mut game = GameState::new(&config).unwrap();
+        let actions_taken = |game: &GameState| game.hand_history.last().unwrap().actions.len();
+        assert!(matches!(game.step_robot(), Err(GameError::NoRobotToAct)), "the human is up");
+
+        // The robot after the big blind acted as the hand opened. Handling the human's call on
+        // its own leaves the blinds waiting to be stepped.
+        assert_eq!(actions_taken(&game), 1);
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Call, amount: None }).unwrap();
+        assert_eq!(game.current_player, 1);
+        let action = game.step_robot().unwrap();
+        assert_eq!(action.player_index, 1);
+        assert_eq!(actions_taken(&game), 3, "only the stepped robot acted");
+        assert_eq!(game.current_player, 2);
+        assert_eq!(game.step_robot().unwrap().player_index, 2);
+        assert_eq!(actions_taken(&game), 4);
+    }
+
+    #[test]
+    fn a_table_of_robots_plays_out_on_a_small_stack() {
+        let config = NewGameQuery { num_players: 8, game_mode: GameMode::RobotPlay, ..Default::default() };
+        let mut game = GameState::new(&config).unwrap();
+        for player in &mut game.players {
+            player.is_robot = true;
+        }
+        // Robots take turns in a loop, so a small thread stack is plenty for a full table.
+        let game = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                game.advance_until_human().unwrap();
+                game
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(game.at_showdown() || game.betting_round_complete());
+        assert!(game.hand_history.last().unwrap().actions.len() >= 7);
+    }
+
+    #[test]
+    fn table_view_marks_the_seat_to_act_and_the_button() {
+        let mut game = test_game(3);
+        let table = game.table_view();
+        assert_eq!(table.seats.len(), 3);
+        assert_eq!(table.dealer, game.dealer);
+        assert_eq!((table.small_blind_seat, table.big_blind_seat), (Some(1), Some(2)), "the opening hand posts blinds too");
+        let to_act: Vec<usize> = table.seats.iter().filter(|s| s.to_act).map(|s| s.seat).collect();
+        assert_eq!(to_act, vec![game.current_player]);
+        assert!(!table.seats[game.current_player].folded);
+
+        for seat in [0, 1] {
+            game.handle_action(Action { player_index: seat, action_type: ActionType::Fold, amount: None }).unwrap();
+        }
+        game.deal_new_hand().unwrap();
+        let table = game.table_view();
+        assert_eq!(table.dealer, 1);
+        assert_eq!((table.small_blind_seat, table.big_blind_seat), (Some(2), Some(0)));
+        assert_eq!(table.seats[0].current_bet, game.big_blind);
+        assert_eq!(table.seats.iter().filter(|s| s.to_act).count(), 1);
+        assert!(table.seats[game.current_player].to_act);
+    }
+
+    #[test]
+    fn a_seeded_game_replays_the_same_robot_actions() {
+        let play = |seed| {
+            let mut game = GameState::new(&NewGameQuery { num_players: 4, seed: Some(seed), ..Default::default() }).unwrap();
+            game.difficulty = 0.0;
+            game.auto_play().unwrap();
+            let hand = game.hand_history.last().unwrap();
+            let actions: Vec<_> = hand.actions.iter().map(|a| (a.action.player_index, a.action.action_type.clone(), a.action.amount)).collect();
+            (actions, hand.player_cards.clone(), hand.community_cards.clone())
+        };
+        let first = play(42);
+        assert!(!first.0.is_empty());
+        for _ in 0..3 {
+            assert_eq!(play(42), first);
+        }
+
+        // Against a human who always checks or calls, the robots' tells repeat as well, and so
+        // do the equities they're drawn from.
+        let tells = |seed| {
+            let config = NewGameQuery { num_players: 4, game_mode: GameMode::RobotPlay, seed: Some(seed), ..Default::default() };
+            let mut game = GameState::new(&config).unwrap();
+            while !game.at_showdown() {
+                let seat = game.current_player;
+                if game.betting_round_complete() {
+                    if game.community_cards.len() == 5 {
+                        game.showdown().unwrap();
+                    } else {
+                        game.deal_next_street().unwrap();
+                    }
+                } else if game.players[seat].is_robot {
+                    game.handle_robot_action().unwrap();
+                } else {
+                    let legal = game.legal_actions(seat).unwrap();
+                    let action_type = if legal.actions.contains(&ActionType::Check) { ActionType::Check } else { ActionType::Call };
+                    game.handle_action(Action { player_index: seat, action_type, amount: None }).unwrap();
+                }
+            }
+            let hand = game.hand_history.last().unwrap();
+            let tells: Vec<_> =
+                hand.actions.iter().map(|a| (a.action.player_index, a.action.action_type.clone(), a.tell.clone())).collect();
+            let equities: Vec<_> = game.players.iter().map(|p| p.equity_history.clone()).collect();
+            (tells, equities)
+        };
+        let first = tells(7);
+        assert!(first.0.iter().any(|(_, _, tell)| tell.is_some()));
+        for _ in 0..3 {
+            assert_eq!(tells(7), first);
+        }
+    }
+
+    #[test]
+    fn robots_only_auto_act_in_robot_play() {
+        let call = Action { player_index: 0, action_type: ActionType::Call, amount: None };
+        let actions_taken = |game: &GameState| game.hand_history.last().unwrap().actions.len();
+
+        let mut study = test_game(3);
+        study.handle_action(call.clone()).unwrap();
+        assert_eq!(actions_taken(&study), 1, "the next robot waits its turn in Simulation");
+        assert_eq!(study.current_player, 1);
+        assert_eq!(study.stats.players[0].favorite_action, "Conservative");
+        study.handle_action(study.robot_decision(1).unwrap()).unwrap();
+        assert_eq!(actions_taken(&study), 2);
+
+        study.auto_play().unwrap();
+        study.deal_new_hand().unwrap();
+        assert_eq!(study.current_player, 1);
+        assert_eq!(actions_taken(&study), 0, "nobody acts preflop until asked");
+
+        let mut robots = GameState::new(&NewGameQuery { num_players: 3, game_mode: GameMode::RobotPlay, ..Default::default() })
+            .unwrap();
+        robots.handle_action(call).unwrap();
+        robots.advance_until_human().unwrap();
+        assert!(actions_taken(&robots) >= 3, "both robots answer the human straight away");
+        assert_eq!(robots.stats.players[0].favorite_action, "Conservative");
+    }
+
+    #[test]
+    fn running_it_twice_splits_the_pot_across_two_boards() {
+        let mut game = test_game(2);
+        rig_hand(&mut game, &[cards("Ah Ad"), cards("Kh Kd")], &cards("2c 7s 9d"));
+        // Cards are popped from the end: the aces hold on the first run, the kings hit on the second.
+        game.deck = cards("5h Kc 4s 3c");
+        game.handle_action(Action { player_index: 0, action_type: ActionType::Raise, amount: Some(1000) }).unwrap();
+        let before_call = game.clone();
+        assert!(before_call.clone().showdown_run_twice().is_err(), "seat 1 can still act");
+        game.handle_action(Action { player_index: 1, action_type: ActionType::Call, amount: None }).unwrap();
+
+        assert_eq!(game.showdown_run_twice().unwrap(), vec![0, 1]);
+        let hand = game.hand_history.last().unwrap();
+        assert_eq!(hand.community_cards, cards("2c 7s 9d 3c 4s"));
+        assert_eq!(hand.second_board, cards("2c 7s 9d Kc 5h"));
+        let won: Vec<u32> = hand.showdown_results.iter().map(|r| r.amount_won).collect();
+        assert_eq!(won, [1000, 1000]);
+        assert_eq!((game.players[0].chips, game.players[1].chips), (1000, 1000));
+        assert_eq!(game.pot, 0);
+        assert!(game.showdown_run_twice().is_err(), "the board is complete");
+    }
+
+    #[tokio::test]
+    async fn replay_rebuilds_a_hand_up_to_an_action() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let mut game = three_handed_after_blinds();
+        let act = |action_type, player_index, amount| Action { player_index, action_type, amount };
+        game.handle_action(act(ActionType::Call, 0, None)).unwrap();
+        game.handle_action(act(ActionType::Raise, 1, Some(40))).unwrap();
+        game.handle_action(act(ActionType::Call, 2, None)).unwrap();
+        game.handle_action(act(ActionType::Call, 0, None)).unwrap();
+        game.deal_flop();
+        game.handle_action(act(ActionType::Bet, 1, Some(50))).unwrap();
+        let flop = game.community_cards.clone();
+        let hand_index = game.hand_history.len() - 1;
+        *GAME_STATE.lock().await = Some(game);
+
+        let at = |action_index| replay(Query(ReplayQuery { hand_index, action_index }));
+        let dealt = at(0).await.0.unwrap();
+        assert_eq!((dealt.pot, dealt.actions.len()), (15, 0));
+        let after_call = at(1).await.0.unwrap();
+        assert_eq!(after_call.pot, 25, "the pot before the raise and calls");
+        assert_eq!(after_call.actions[0].action.player_index, 0);
+        assert!(after_call.community_cards.is_empty());
+        let preflop_done = at(4).await.0.unwrap();
+        assert_eq!((preflop_done.pot, preflop_done.phase), (120, GamePhase::PreFlop));
+        let flop_bet = at(5).await.0.unwrap();
+        assert_eq!((flop_bet.pot, flop_bet.phase), (170, GamePhase::Flop));
+        assert_eq!(flop_bet.community_cards, flop);
+
+        assert_eq!(at(6).await.0.unwrap_err(), GameError::NoSuchAction { index: 6, recorded: 5 });
+        let missing = replay(Query(ReplayQuery { hand_index: hand_index + 1, action_index: 0 })).await.0;
+        assert_eq!(missing.unwrap_err(), GameError::NoSuchHand { index: hand_index + 1 });
+    }
+
+    #[tokio::test]
+    async fn the_deck_is_never_sent_to_clients() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        std::env::set_var("POKERBOT_DEBUG", "1");
+        let game = test_game(3);
+        *GAME_STATE.lock().await = Some(game.clone());
+
+        let Json(response) = deal_flop().await;
+        let json = serde_json::to_value(response).unwrap();
+        assert!(json.get("deck").is_none());
+        assert!(json.get("players").is_some());
+
+        let Json(full) = get_state().await.unwrap();
+        let json = serde_json::to_value(full).unwrap();
+        assert_eq!(json["deck"].as_array().unwrap().len(), game.deck.len() - 3);
+    }
+
+    #[tokio::test]
+    async fn shutdown_saves_the_active_game() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let path = std::env::temp_dir().join(format!("pokerbot-shutdown-{}.json", std::process::id()));
+        let mut game = test_game(3);
+        game.deal_flop();
+        *GAME_STATE.lock().await = Some(game.clone());
+
+        shutdown_signal(async {}, Some(path.clone())).await;
+        let restored = load_game(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.community_cards, game.community_cards);
+        assert_eq!(restored.deck, game.deck);
+        assert_eq!(restored.pot, game.pot);
+
+        // A save with a card duplicated is refused.
+        game.deck.push(game.community_cards[0]);
+        save_game(&path, &game).unwrap();
+        let err = load_game(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.contains("Duplicate card"), "{}", err);
+
+        shutdown_signal(async {}, None).await;
+    }
+
+    #[tokio::test]
+    async fn robots_with_a_think_delay_act_after_it() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        let delay = std::time::Duration::from_millis(40);
+        let config = NewGameQuery {
+            num_players: 3,
+            game_mode: GameMode::RobotPlay,
+            robot_delay_ms: Some(delay.as_millis() as u64),
+            ..Default::default()
+        };
+        *GAME_STATE.lock().await = Some(GameState::new(&config).unwrap());
+        let robot_actions = |game: &GameState| {
+            let actions = &game.hand_history.last().unwrap().actions;
+            actions.iter().filter(|a| a.action.player_index != 0).count()
+        };
+
+        let started = std::time::Instant::now();
+        let call = PlayerAction { player_index: 0, action_type: ActionType::Call, amount: None };
+        let view = player_action(JsonExtractor(call)).await.0.unwrap();
+        assert_eq!(robot_actions(&view), 0, "the human's action returns before the robots think");
+
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            let state = GAME_STATE.lock().await;
+            if !state.as_ref().unwrap().robot_to_act() {
+                break;
+            }
+            assert!(started.elapsed() < std::time::Duration::from_secs(5), "robots never finished");
+        }
+        let done = robot_actions(GAME_STATE.lock().await.as_ref().unwrap());
+        assert!(done >= 2, "both robots act");
+        assert!(started.elapsed() >= delay * done as u32);
+    }
+
+    #[tokio::test]
+    async fn back_to_back_deals_still_leave_each_robot_its_think_time() {
+        let _guard = GLOBAL_GAME_LOCK.lock().await;
+        // Longer than a robot takes to decide, so a second loop acting early would show.
+        let delay = 400;
+        let config = NewGameQuery {
+            num_players: 3,
+            game_mode: GameMode::RobotPlay,
+            robot_delay_ms: Some(delay),
+            ..Default::default()
+        };
+        *GAME_STATE.lock().await = Some(GameState::new(&config).unwrap());
+
+        // Each deal hands the turn to a robot; only one of them may start the robots thinking.
+        assert_eq!(deal_flop().await.0.community_cards.len(), 3);
+        assert_eq!(deal_turn().await.0.community_cards.len(), 4);
+        let started = std::time::Instant::now();
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            if !GAME_STATE.lock().await.as_ref().unwrap().robot_to_act() {
+                break;
+            }
+            assert!(started.elapsed() < std::time::Duration::from_secs(5), "robots never finished");
+        }
+        let game = GAME_STATE.lock().await.clone().unwrap();
+        let robot_actions: Vec<_> = game.hand_history.last().unwrap().actions.iter().filter(|a| a.action.player_index != 0).collect();
+        assert!(robot_actions.len() >= 2, "both robots act");
+        for action in robot_actions {
+            assert!(action.elapsed_ms >= delay, "a robot acted {}ms after the last action", action.elapsed_ms);
+        }
+    }
+
+    #[test]
+    fn zero_simulations_split_the_pot_evenly() {
+        let (hero, villain) = (cards("Ah Kh"), cards("Qs Qd"));
+        let deck = deck_without(&[hero.clone(), villain.clone()].concat());
+        let breakdown = simulate_equity_breakdown(&hero, &[villain], &[], &deck, &[], 0, Variant::Holdem);
+        assert_eq!(breakdown.pot_share(), 0.5);
+        assert_eq!(breakdown.win + breakdown.tie + breakdown.lose, 1.0);
+        assert_eq!(simulate_win_probability_hidden(&hero, 2, &[], &deck, &[], 0, Variant::Holdem), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn a_shared_straight_is_mostly_a_tie() {
+        // Both players hold the nine to the board's open-ended run, so they split nearly every river.
+        let deck = deck_without(&cards("9h 2c 9d 3s 5c 6d 7h 8s"));
+        let breakdown = simulate_equity_breakdown(
+            &cards("9h 2c"),
+            &[cards("9d 3s")],
+            &cards("5c 6d 7h 8s"),
+            &deck,
+            &[],
+            2000,
+            Variant::Holdem,
+        );
+        assert!(breakdown.tie > 0.9, "tie fraction {}", breakdown.tie);
+        assert!((breakdown.win + breakdown.tie + breakdown.lose - 1.0).abs() < 1e-9);
+        assert!((breakdown.pot_share() - (breakdown.win + breakdown.tie / 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn more_simulations_narrow_the_confidence_interval() {
+        let hero = cards("Ah Kh");
+        let villain = [cards("Qs Qd")];
+        let deck = deck_without(&cards("Ah Kh Qs Qd"));
+        let width = |sims| {
+            let breakdown = simulate_equity_breakdown(&hero, &villain, &[], &deck, &[], sims, Variant::Holdem);
+            let (low, high) = confidence_interval_95(breakdown.pot_share(), breakdown.standard_error);
+            assert!(low <= breakdown.pot_share() && breakdown.pot_share() <= high);
+            high - low
+        };
+        let (rough, fine) = (width(100), width(10_000));
+        assert!(fine < rough / 5.0, "100 sims: ±{}, 10000 sims: ±{}", rough / 2.0, fine / 2.0);
+        assert!((standard_error(0.5, 10_000) - 0.005).abs() < 1e-12);
+
+        let board = cards("2h 7h 9c Js");
+        let river_deck = deck_without(&[hero.clone(), villain.concat(), board.clone()].concat());
+        let exact = exact_equity_breakdown(&hero, &villain, &board, &river_deck, Variant::Holdem);
+        assert_eq!(exact.standard_error, 0.0);
+    }
+
+    #[test]
+    fn a_rigged_deck_is_dealt_in_order() {
+        let mut deck = cards("Ah As Kd Kc");
+        deck.extend(deck_without(&deck));
+        let game = GameState::new_with_deck(&NewGameQuery::default(), deck.clone()).unwrap();
+        let hole_cards = &game.players[0].cards;
+        assert_eq!(hole_cards.len(), 2);
+        assert!(cards("Ah As").iter().all(|c| hole_cards.contains(c)));
+        assert!(cards("Kd Kc").iter().all(|c| game.players[1].cards.contains(c)));
+        assert_eq!(game.deck.len(), 48);
+        game.validate_no_duplicates().unwrap();
+
+        assert!(GameState::new_with_deck(&NewGameQuery::default(), deck[1..].to_vec()).is_err());
+        deck[51] = deck[0];
+        assert!(GameState::new_with_deck(&NewGameQuery::default(), deck).is_err());
+    }
+
+    #[test]
+    fn ranges_take_the_strongest_starting_hands() {
+        assert_eq!(Range::preset(RangePreset::Any).combos.len(), 1326);
+        let has = |range: &Range, hand: &str| {
+            let hand = cards(hand);
+            range.combos.iter().any(|c| hand.contains(&c[0]) && hand.contains(&c[1]))
+        };
+        let tight = Range::preset(RangePreset::Top5);
+        assert!((66..=90).contains(&tight.combos.len()), "{} combos", tight.combos.len());
+        assert!(has(&tight, "Ah As"));
+        assert!(has(&tight, "Kd Kc"));
+        assert!(!has(&tight, "7h 2c"));
+        let wider = Range::preset(RangePreset::Top25);
+        assert!(tight.combos.iter().all(|c| wider.combos.contains(c)));
+        assert!(has(&wider, "Jh Th"));
+    }
+
+    #[test]
+    fn equity_shifts_as_the_opponent_range_tightens() {
+        // King-queen does well against random hands but is dominated by a tight range.
+        let hero = cards("Kh Qd");
+        let unseen = deck_without(&hero);
+        let equity = |preset| {
+            let ranges = [Range::preset(preset)];
+            simulate_win_probability_against_ranges(&hero, &ranges, &[], &unseen, &[], 4000).0
+        };
+        let (any, top25, top5) = (equity(RangePreset::Any), equity(RangePreset::Top25), equity(RangePreset::Top5));
+        assert!((0.58..0.68).contains(&any), "KQ against any hand: {}", any);
+        assert!(any > top25 + 0.05 && top25 > top5 + 0.05, "any {} > top 25% {} > top 5% {}", any, top25, top5);
+
+        // Against a range of nothing but kings, aces hold up about 82% of the time.
+        let aces = cards("Ah Ad");
+        let unseen = deck_without(&aces);
+        let kings = Range { combos: vec![[card(Rank::King, Suit::Spades), card(Rank::King, Suit::Clubs)]] };
+        let (against_kings, _) = simulate_win_probability_against_ranges(&aces, &[kings], &[], &unseen, &[], 4000);
+        assert!((0.77..0.87).contains(&against_kings), "AA against KK: {}", against_kings);
+    }
+
+    #[test]
+    fn folded_players_are_not_counted_as_opponents() {
+        let mut game = test_game(3);
+        game.equity_mode = EquityMode::Exact;
+        let folder = game.current_player;
+        let mut hands = vec![cards("2c 3d"), cards("2d 3c")];
+        hands.insert(folder, cards("Ac Ad"));
+        let flop = cards("Ah Kh Qh");
+        rig_hand(&mut game, &hands, &flop);
+        game.deck = deck_without(&[hands.concat(), flop, cards("8s")].concat());
+        game.deck.push(card(Rank::Eight, Suit::Spades));
+
+        game.handle_action(Action { player_index: folder, action_type: ActionType::Fold, amount: None }).unwrap();
+        game.deal_turn();
+        // The two live hands chop every river; a folded "opponent" playing the board would take a share.
+        let live: Vec<f64> = (0..3).filter(|&i| i != folder).map(|i| game.players[i].win_probability).collect();
+        assert_eq!(live, vec![0.5, 0.5]);
+        assert_eq!(game.players[folder].win_probability, 0.0);
+    }
+
+    #[test]
+    fn game_state_uses_the_configured_equity_mode() {
+        let game = GameState::new(&NewGameQuery { num_players: 3, equity_mode: EquityMode::Exact, ..Default::default() }).unwrap();
+        assert_eq!(game.equity_mode, EquityMode::Exact);
+        let total: f64 = game.players.iter().map(|p| p.win_probability).sum();
+        // Equities against the actual dealt hands partition the pot, up to sampling noise.
+        assert!((total - 1.0).abs() < 0.15, "equities sum to {}", total);
+
+        let game = test_game(3);
+        assert_eq!(game.equity_mode, EquityMode::Hidden);
+        assert!(game.players.iter().all(|p| (0.0..=1.0).contains(&p.win_probability)));
+    }
+
+    /// Brute-force cross-check of `evaluate_best_hand` against an independent seven-card ranker.
+    mod evaluator_harness {
+        use super::*;
+
+        const CASES: usize = 3000;
+
+        /// Highest straight in a rank mask (bit v set for value v, ace also on bit 1), if any.
+        fn straight_high(mask: u16) -> Option<u8> {
+            (5..=14u8).rev().find(|&high| (0..5).all(|i| mask & (1 << (high - i)) != 0))
+        }
+
+        fn with_low_ace(mask: u16) -> u16 {
+            if mask & (1 << 14) != 0 {
+                mask | 0b10
+            } else {
+                mask
+            }
+        }
+
+        /// Ranks seven cards directly, without enumerating five-card subsets, as one integer:
+        /// category in the top bits, then up to five tie-break values of four bits each.
+        fn canonical_rank(cards: &[Card]) -> (u8, u32) {
+            let mut counts = [0usize; 15];
+            let mut suit_masks = [0u16; 4];
+            for card in cards {
+                counts[card.value() as usize] += 1;
+                suit_masks[card.suit as usize] |= 1 << card.value();
+            }
+            let all_mask = with_low_ace(suit_masks.iter().fold(0, |acc, m| acc | m));
+            let flush_mask = suit_masks.iter().copied().find(|m| m.count_ones() >= 5);
+            let ranks_with = |n: usize| (2..=14u8).rev().filter(move |&v| counts[v as usize] == n);
+            let top = |exclude: &[u8], n: usize| -> Vec<u8> {
+                (2..=14u8).rev().filter(|v| counts[*v as usize] > 0 && !exclude.contains(v)).take(n).collect()
+            };
+
+            let trips = ranks_with(3).next();
+            let full_house_pair =
+                trips.and_then(|t| (2..=14u8).rev().find(|&v| v != t && counts[v as usize] >= 2));
+
+            let (category, kickers): (u8, Vec<u8>) = if let Some(high) =
+                flush_mask.and_then(|m| straight_high(with_low_ace(m)))
+            {
+                (if high == 14 { 9 } else { 8 }, vec![high])
+            } else if let Some(quad) = ranks_with(4).next() {
+                (7, [vec![quad], top(&[quad], 1)].concat())
+            } else if let (Some(trips), Some(pair)) = (trips, full_house_pair) {
+                (6, vec![trips, pair])
+            } else if let Some(mask) = flush_mask {
+                (5, (2..=14u8).rev().filter(|v| mask & (1 << v) != 0).take(5).collect())
+            } else if let Some(high) = straight_high(all_mask) {
+                (4, vec![high])
+            } else if let Some(trips) = trips {
+                (3, [vec![trips], top(&[trips], 2)].concat())
+            } else {
+                let pairs: Vec<u8> = ranks_with(2).take(2).collect();
+                match pairs.len() {
+                    2 => (2, [pairs.clone(), top(&pairs, 1)].concat()),
+                    1 => (1, [pairs.clone(), top(&pairs, 3)].concat()),
+                    _ => (0, top(&[], 5)),
+                }
+            };
+            let packed = kickers
+                .iter()
+                .chain(std::iter::repeat(&0))
+                .take(5)
+                .fold(0u32, |acc, &v| acc << 4 | v as u32);
+            (category, (category as u32) << 20 | packed)
+        }
+
+        #[test]
+        fn canonical_rank_spot_checks() {
+            assert_eq!(canonical_rank(&cards("Ah Kh Qh Jh Th 2c 3d")).0, 9);
+            assert_eq!(canonical_rank(&cards("Ah 2h 3h 4h 5h Kc Kd")).0, 8);
+            assert_eq!(canonical_rank(&cards("Ks Kh Kd 2c 2d 2h 9s")).0, 6);
+            let wheel = canonical_rank(&cards("Ac 2d 3h 4s 5c 9d Jh"));
+            assert!(wheel < canonical_rank(&cards("2d 3h 4s 5c 6c 9d Jh")));
+            let two_pair = canonical_rank(&cards("Ac Ad 9h 9s 4c 4d 2h"));
+            assert!(two_pair < canonical_rank(&cards("Ac Ad 9h 9s 5c 4d 2h")));
+        }
+
+        #[test]
+        fn best_hand_ordering_matches_the_canonical_rank() {
+            let cases: Vec<(Vec<Card>, Hand, (u8, u32))> = (0..CASES)
+                .map(|_| {
+                    let seven = new_shuffled_deck()[..7].to_vec();
+                    let best = evaluate_best_hand(&seven).unwrap();
+                    let canonical = canonical_rank(&seven);
+                    (seven, best, canonical)
+                })
+                .collect();
+
+            for (seven, best, (category, _)) in &cases {
+                assert_eq!(
+                    best.hand_type.clone() as u8,
+                    *category,
+                    "hand type disagrees for {}: evaluator says {:?}",
+                    format_cards(seven),
+                    best
+                );
+            }
+            for (i, (a, best_a, (_, rank_a))) in cases.iter().enumerate() {
+                for (b, best_b, (_, rank_b)) in &cases[i + 1..] {
+                    assert_eq!(
+                        best_a.cmp(best_b),
+                        rank_a.cmp(rank_b),
+                        "ordering disagrees between {} ({:?}) and {} ({:?})",
+                        format_cards(a),
+                        best_a,
+                        format_cards(b),
+                        best_b
+                    );
+                }
+            }
+        }
+    }
 }
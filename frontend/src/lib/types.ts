@@ -35,7 +35,6 @@ export interface Player {
 }
 
 export interface GameState {
-    deck: Card[];
     players: Player[];
     community_cards: Card[];
     pot: number;